@@ -0,0 +1,272 @@
+// Names of still lifes, oscillators and spaceships in the apgcode notation of Catagolue, such as
+// xs4_33 for the block, see https://conwaylife.com/wiki/Apgcode
+use std::fmt;
+
+use crate::detect::{Cycle, CycleDetector};
+use crate::rule::Rule;
+use crate::sparse::SparseLife;
+use crate::{Coord, Life};
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+// Longest period looked for when identifying an object
+pub const MAX_PERIOD: u64 = 1024;
+// Rows of cells encoded by a single character
+const STRIP_HEIGHT: i32 = 5;
+
+type Transform = fn(i32, i32) -> (i32, i32);
+
+// The 8 rotations and reflections of the square
+const SYMMETRIES: [Transform; 8] = [
+    |x, y| (x, y),
+    |x, y| (-y, x),
+    |x, y| (-x, -y),
+    |x, y| (y, -x),
+    |x, y| (-x, y),
+    |x, y| (x, -y),
+    |x, y| (y, x),
+    |x, y| (-y, -x),
+];
+
+// Extended Wechsler format: strips of 5 rows separated by z, with a character for the cells of
+// every column of a strip and shorthands for runs of empty columns
+fn wechsler(cells: &[Coord]) -> String {
+    let (min_x, min_y) = (
+        cells.iter().map(|coord| coord.x).min().unwrap_or(0),
+        cells.iter().map(|coord| coord.y).min().unwrap_or(0),
+    );
+    let (width, height) = (
+        cells
+            .iter()
+            .map(|coord| coord.x - min_x + 1)
+            .max()
+            .unwrap_or(0),
+        cells
+            .iter()
+            .map(|coord| coord.y - min_y + 1)
+            .max()
+            .unwrap_or(0),
+    );
+    let strips = (height + STRIP_HEIGHT - 1) / STRIP_HEIGHT;
+    let mut columns = vec![vec![0u8; width as usize]; strips as usize];
+    for coord in cells {
+        let (x, y) = (coord.x - min_x, coord.y - min_y);
+        columns[(y / STRIP_HEIGHT) as usize][x as usize] |= 1 << (y % STRIP_HEIGHT);
+    }
+    let mut code = String::new();
+    for (i, strip) in columns.iter().enumerate() {
+        if i > 0 {
+            code.push('z');
+        }
+        let end = strip
+            .iter()
+            .rposition(|&column| column != 0)
+            .map_or(0, |i| i + 1);
+        let mut empty = 0;
+        for &column in &strip[..end] {
+            if column == 0 {
+                empty += 1;
+                continue;
+            }
+            push_empty_columns(&mut code, empty);
+            empty = 0;
+            code.push(DIGITS[column as usize] as char);
+        }
+    }
+    code
+}
+
+// w and x stand for 2 and 3 empty columns, and y followed by a digit for 4 up to 39
+fn push_empty_columns(code: &mut String, mut empty: usize) {
+    while empty > 0 {
+        let run = empty.min(39);
+        match run {
+            1 => code.push('0'),
+            2 => code.push('w'),
+            3 => code.push('x'),
+            run => {
+                code.push('y');
+                code.push(DIGITS[run - 4] as char);
+            }
+        }
+        empty -= run;
+    }
+}
+
+// Canonical apgcode of an object from its phases, one per generation of its period: xs for still
+// lifes, xp for oscillators and xq for spaceships. The body is the shortest encoding of any
+// phase in any orientation, the first in ASCII order among the shortest
+pub fn encode(phases: &[Vec<Coord>], spaceship: bool) -> String {
+    let prefix = match phases.len() {
+        _ if spaceship => format!("xq{}", phases.len()),
+        1 => format!("xs{}", phases[0].len()),
+        period => format!("xp{}", period),
+    };
+    let body = phases
+        .iter()
+        .flat_map(|cells| {
+            SYMMETRIES.iter().map(move |symmetry| {
+                let moved: Vec<Coord> = cells
+                    .iter()
+                    .map(|coord| {
+                        let (x, y) = symmetry(coord.x, coord.y);
+                        Coord { x, y }
+                    })
+                    .collect();
+                wechsler(&moved)
+            })
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default();
+    format!("{}_{}", prefix, body)
+}
+
+// Runs an object on its own for up to max_period generations, returning its apgcode and cycle
+// when it is a still life, oscillator or spaceship from the start
+pub fn identify(rule: Rule, cells: Vec<Coord>, max_period: u64) -> Option<(String, Cycle)> {
+    if cells.is_empty() {
+        return None;
+    }
+    let mut life = SparseLife::from_iter(rule, cells.into_iter()).ok()?;
+    let mut detector = CycleDetector::new(max_period as usize + 1);
+    let mut phases = vec![life.live_cells()];
+    detector.record(0, &life);
+    for generation in 1..=max_period {
+        life.step();
+        let Some(cycle) = detector.record(generation, &life) else {
+            phases.push(life.live_cells());
+            continue;
+        };
+        if cycle.start != 0 {
+            return None;
+        }
+        return Some((encode(&phases, cycle.is_spaceship()), cycle));
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApgcodeError {
+    // Codes start with xs, xp or xq, a number and an underscore
+    MissingPrefix,
+    InvalidCharacter { character: char, position: usize },
+    // A y at the end, without the number of empty columns after it
+    Truncated,
+}
+
+impl fmt::Display for ApgcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingPrefix => write!(
+                f,
+                "apgcode must start with xs, xp or xq, a number and _, e.g. xs4_33"
+            ),
+            Self::InvalidCharacter {
+                character,
+                position,
+            } => write!(
+                f,
+                "unexpected `{}` at position {} of the apgcode",
+                character, position
+            ),
+            Self::Truncated => write!(f, "apgcode ends in the middle of a run of empty columns"),
+        }
+    }
+}
+
+// Live cells of one phase of the object named by an apgcode, with its top left corner at (0, 0)
+pub fn decode(code: &str) -> Result<Vec<Coord>, ApgcodeError> {
+    let code = code.trim();
+    let (prefix, body) = code.split_once('_').ok_or(ApgcodeError::MissingPrefix)?;
+    let number = prefix
+        .strip_prefix("xs")
+        .or_else(|| prefix.strip_prefix("xp"))
+        .or_else(|| prefix.strip_prefix("xq"))
+        .ok_or(ApgcodeError::MissingPrefix)?;
+    if number.is_empty() || !number.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(ApgcodeError::MissingPrefix);
+    }
+    let digit = |character: char| DIGITS.iter().position(|&digit| digit as char == character);
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let mut characters = body
+        .chars()
+        .enumerate()
+        .map(|(i, ch)| (prefix.len() + 2 + i, ch));
+    while let Some((position, character)) = characters.next() {
+        match character {
+            'w' => x += 2,
+            'x' => x += 3,
+            'y' => {
+                let (position, character) = characters.next().ok_or(ApgcodeError::Truncated)?;
+                let run = digit(character).ok_or(ApgcodeError::InvalidCharacter {
+                    character,
+                    position,
+                })?;
+                x += 4 + run as i32;
+            }
+            'z' => (x, y) = (0, y + STRIP_HEIGHT),
+            _ => {
+                let column = digit(character).filter(|&column| column < 32).ok_or(
+                    ApgcodeError::InvalidCharacter {
+                        character,
+                        position,
+                    },
+                )?;
+                for row in (0..STRIP_HEIGHT).filter(|row| column & (1 << row) != 0) {
+                    cells.push(Coord { x, y: y + row });
+                }
+                x += 1;
+            }
+        }
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifies_what_it_decodes() {
+        // Block, blinker, glider, beehive and lightweight spaceship
+        for code in ["xs4_33", "xp2_7", "xq4_153", "xs6_696", "xq4_6frc"] {
+            let cells = decode(code).unwrap();
+            let identified = identify(Rule::default(), cells, MAX_PERIOD);
+            assert_eq!(identified.map(|(code, _)| code).as_deref(), Some(code));
+        }
+    }
+
+    #[test]
+    fn round_trips_runs_of_empty_columns() {
+        // Gaps of 2, 3, 5 and 40 columns, the last one too long for a single y
+        let code = "xs5_1w1x1y11yz01";
+        let cells = decode(code).unwrap();
+        let columns: Vec<i32> = cells.iter().map(|coord| coord.x).collect();
+        assert_eq!(columns, [0, 3, 7, 13, 54]);
+        assert!(cells.iter().all(|coord| coord.y == 0));
+        assert_eq!(encode(&[cells], false), code);
+    }
+
+    #[test]
+    fn round_trips_strips() {
+        // A vertical line of 7 cells takes two strips of 5 rows
+        let code = "xp2_vz3";
+        let cells = decode(code).unwrap();
+        assert_eq!(cells.len(), 7);
+        assert_eq!(wechsler(&cells), "vz3");
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        assert_eq!(decode("33"), Err(ApgcodeError::MissingPrefix));
+        assert_eq!(decode("xs_33"), Err(ApgcodeError::MissingPrefix));
+        assert_eq!(decode("xs4_3y"), Err(ApgcodeError::Truncated));
+        assert_eq!(
+            decode("xs4_3!"),
+            Err(ApgcodeError::InvalidCharacter {
+                character: '!',
+                position: 6
+            })
+        );
+    }
+}
@@ -0,0 +1,309 @@
+// Dense engine packing 64 cells into every u64, so a whole word of cells is updated at once.
+// Neighbor counts are summed with bitwise adders (SWAR), one bit plane per bit of the count.
+use crate::rule::Rule;
+use crate::{CellStatus, Coord, Life, Topology, GOL};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+#[derive(Debug, Clone)]
+pub struct BitGrid {
+    width: u32,
+    height: u32,
+    words_per_row: usize,
+    // Row major, bit i of word w in a row is the cell at x = w * 64 + i
+    rows: Vec<u64>,
+    rule: Rule,
+    topology: Topology,
+    // Number of horizontal bands stepped in parallel
+    threads: usize,
+}
+
+impl BitGrid {
+    pub fn from_gol(gol: &GOL) -> Self {
+        let words_per_row = (gol.width as usize).div_ceil(WORD_BITS);
+        let mut rows = vec![0; words_per_row * gol.height as usize];
+        for y in 0..gol.height as i32 {
+            for x in 0..gol.width as i32 {
+                let coord = Coord { x, y };
+                if gol.grid[coord.index_in(gol.width, gol.height)] == CellStatus::Alive {
+                    rows[y as usize * words_per_row + x as usize / WORD_BITS] |=
+                        1 << (x as usize % WORD_BITS);
+                }
+            }
+        }
+        Self {
+            width: gol.width,
+            height: gol.height,
+            words_per_row,
+            rows,
+            rule: gol.rule,
+            topology: gol.topology,
+            threads: gol.threads,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn to_gol(&self) -> GOL {
+        GOL::from_iter_with_topology(
+            self.width,
+            self.height,
+            self.topology,
+            self.live_cells().into_iter(),
+        )
+        .with_rule(self.rule)
+        .with_threads(self.threads)
+    }
+
+    // Computes the next generation of the rows starting at first_row into rows
+    fn step_band(&self, first_row: usize, rows: &mut [u64]) {
+        let mut planes: [Vec<u64>; 8] = Default::default();
+        planes
+            .iter_mut()
+            .for_each(|plane| plane.resize(self.words_per_row, 0));
+        // Stands in for the rows off a bounded board
+        let zeros = vec![0; self.words_per_row];
+        for (y, out) in rows.chunks_mut(self.words_per_row).enumerate() {
+            self.step_row(first_row + y, &mut planes, &zeros, out);
+        }
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.rows[y * self.words_per_row..(y + 1) * self.words_per_row]
+    }
+
+    // Row y + dy, or None when it falls off a bounded board
+    fn neighbor_row(&self, y: usize, dy: isize) -> Option<&[u64]> {
+        let height = self.height as isize;
+        let ny = y as isize + dy;
+        match self.topology {
+            Topology::Torus => Some(self.row(ny.rem_euclid(height) as usize)),
+            Topology::Bounded if (0..height).contains(&ny) => Some(self.row(ny as usize)),
+            Topology::Bounded => None,
+        }
+    }
+
+    // Bit x set to the cell at x - 1, i.e. the row shifted towards higher x
+    fn west_neighbors(&self, row: &[u64], out: &mut [u64]) {
+        let mut carry = 0;
+        for (word, out) in row.iter().zip(out.iter_mut()) {
+            *out = (word << 1) | carry;
+            carry = word >> (WORD_BITS - 1);
+        }
+        if self.topology == Topology::Torus {
+            let last = self.width as usize - 1;
+            out[0] |= (row[last / WORD_BITS] >> (last % WORD_BITS)) & 1;
+        }
+    }
+
+    // Bit x set to the cell at x + 1, i.e. the row shifted towards lower x
+    fn east_neighbors(&self, row: &[u64], out: &mut [u64]) {
+        let mut carry = 0;
+        for (word, out) in row.iter().zip(out.iter_mut()).rev() {
+            *out = (word >> 1) | carry;
+            carry = word << (WORD_BITS - 1);
+        }
+        if self.topology == Topology::Torus {
+            let last = self.width as usize - 1;
+            out[last / WORD_BITS] |= (row[0] & 1) << (last % WORD_BITS);
+        }
+    }
+
+    // Mask of the bits of the last word in a row that are actual cells
+    fn last_word_mask(&self) -> u64 {
+        match self.width as usize % WORD_BITS {
+            0 => u64::MAX,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    // Next generation of row y, written to out. planes is scratch space for the 8 neighbor rows,
+    // and zeros an empty row
+    fn step_row(&self, y: usize, planes: &mut [Vec<u64>; 8], zeros: &[u64], out: &mut [u64]) {
+        let words = self.words_per_row;
+        let [north, south, north_east, north_west, east, west, south_east, south_west] = planes;
+        north.copy_from_slice(self.neighbor_row(y, -1).unwrap_or(zeros));
+        south.copy_from_slice(self.neighbor_row(y, 1).unwrap_or(zeros));
+        for (dy, east, west) in [
+            (-1, north_east, north_west),
+            (0, east, west),
+            (1, south_east, south_west),
+        ] {
+            let row = self.neighbor_row(y, dy).unwrap_or(zeros);
+            self.west_neighbors(row, west);
+            self.east_neighbors(row, east);
+        }
+
+        let current = self.row(y);
+        for w in 0..words {
+            // Ripple carry adder over the 8 neighbor planes, count = s3 s2 s1 s0 in binary
+            let (mut s0, mut s1, mut s2, mut s3) = (0u64, 0u64, 0u64, 0u64);
+            for plane in planes.iter() {
+                let bit = plane[w];
+                let c0 = s0 & bit;
+                s0 ^= bit;
+                let c1 = s1 & c0;
+                s1 ^= c0;
+                let c2 = s2 & c1;
+                s2 ^= c1;
+                s3 |= c2;
+            }
+
+            let alive = current[w];
+            let mut next = 0;
+            for n in 0..=8 {
+                let bit_matches = |plane: u64, bit: usize| {
+                    if n & (1 << bit) != 0 {
+                        plane
+                    } else {
+                        !plane
+                    }
+                };
+                let count_is_n = bit_matches(s0, 0)
+                    & bit_matches(s1, 1)
+                    & bit_matches(s2, 2)
+                    & bit_matches(s3, 3);
+                if self.rule.survives(n) {
+                    next |= count_is_n & alive;
+                }
+                if self.rule.is_born(n) {
+                    next |= count_is_n & !alive;
+                }
+            }
+            out[w] = next;
+        }
+        out[words - 1] &= self.last_word_mask();
+    }
+}
+
+impl Life for BitGrid {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn is_alive(&self, coord: &Coord) -> bool {
+        self.topology
+            .index(coord, self.width, self.height)
+            .is_some_and(|idx| {
+                let (x, y) = (idx % self.width as usize, idx / self.width as usize);
+                (self.row(y)[x / WORD_BITS] >> (x % WORD_BITS)) & 1 == 1
+            })
+    }
+
+    fn set_alive(&mut self, coord: &Coord, alive: bool) {
+        if let Some(idx) = self.topology.index(coord, self.width, self.height) {
+            let (x, y) = (idx % self.width as usize, idx / self.width as usize);
+            let word = &mut self.rows[y * self.words_per_row + x / WORD_BITS];
+            if alive {
+                *word |= 1 << (x % WORD_BITS);
+            } else {
+                *word &= !(1 << (x % WORD_BITS));
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let mut next_rows = vec![0; self.rows.len()];
+        let band_rows = (self.height as usize).div_ceil(self.threads).max(1);
+        let bands = next_rows
+            .chunks_mut(band_rows * self.words_per_row)
+            .enumerate();
+        if self.threads <= 1 {
+            bands.for_each(|(band, rows)| self.step_band(band * band_rows, rows));
+        } else {
+            let grid = &*self;
+            std::thread::scope(|scope| {
+                for (band, rows) in bands {
+                    scope.spawn(move || grid.step_band(band * band_rows, rows));
+                }
+            });
+        }
+        self.rows = next_rows;
+    }
+
+    fn live_cells(&self) -> Vec<Coord> {
+        (0..self.height as i32)
+            .flat_map(|y| (0..self.width as i32).map(move |x| Coord { x, y }))
+            .filter(|coord| self.is_alive(coord))
+            .collect()
+    }
+
+    fn torus_size(&self) -> Option<(u32, u32)> {
+        (self.topology == Topology::Torus).then_some((self.width, self.height))
+    }
+
+    fn board_size(&self) -> Option<(u32, u32)> {
+        Some((self.width, self.height))
+    }
+
+    fn viewport(&self) -> (Coord, u32, u32) {
+        (Coord { x: 0, y: 0 }, self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{Rng, Xoshiro256, LCG};
+    use crate::soup::Soup;
+
+    fn soup(width: u32, height: u32, topology: Topology, rng: &mut dyn Rng) -> GOL {
+        let cells = Soup::new(width, height, 0.4).generate(rng);
+        GOL::from_iter_with_topology(width, height, topology, cells.into_iter())
+    }
+
+    #[test]
+    fn steps_like_gol() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        for topology in [Topology::Torus, Topology::Bounded] {
+            for width in [64, 128, 1, 63, 65, 70] {
+                for (seed, height) in [1, 2, 3, 17].into_iter().enumerate() {
+                    for rule in [Rule::default(), highlife] {
+                        let mut gol = soup(width, height, topology, &mut LCG::new(seed as u64))
+                            .with_rule(rule);
+                        let mut grid = BitGrid::from_gol(&gol);
+                        for generation in 1..=20 {
+                            gol.step();
+                            grid.step();
+                            assert_eq!(
+                                grid.to_gol().live_cells(),
+                                gol.live_cells(),
+                                "{:?} {}x{} {} at generation {}",
+                                topology,
+                                width,
+                                height,
+                                rule,
+                                generation
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn threads_step_like_a_single_thread() {
+        for topology in [Topology::Torus, Topology::Bounded] {
+            // Bands of 1 row, bands that do not divide the height, and more threads than rows
+            for (width, height) in [(70, 1), (65, 2), (64, 7), (1, 9), (130, 16)] {
+                for threads in [2, 3, 4, 8, 40] {
+                    let gol = || soup(width, height, topology, &mut Xoshiro256::new(height as u64));
+                    let mut sequential = BitGrid::from_gol(&gol());
+                    let mut threaded = BitGrid::from_gol(&gol().with_threads(threads));
+                    for generation in 1..=20 {
+                        sequential.step();
+                        threaded.step();
+                        assert_eq!(
+                            threaded.rows, sequential.rows,
+                            "{:?} {}x{} with {} threads at generation {}",
+                            topology, width, height, threads, generation
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+// Census of random soups in the style of apgsearch: every soup runs on the infinite plane until
+// its population settles, then the ash is split into separate objects which are named by their
+// apgcode and tallied.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::apgcode;
+use crate::rng::Seed;
+use crate::rule::Rule;
+use crate::sparse::{SparseError, SparseLife};
+use crate::{Coord, Life};
+
+// Soups still changing after that many generations are given up on
+const MAX_GENERATIONS: u64 = 20_000;
+// Longest period of the population that counts as settled, the ash of a soup usually mixes
+// periods 1, 2, 3 and 15
+const MAX_PERIOD: usize = 30;
+// Generations the population has to repeat for
+const SETTLED_FOR: usize = 2 * MAX_PERIOD;
+// Cells at most that far apart belong to the same object
+const OBJECT_SPACING: i32 = 1;
+// Generations whose cells make up the footprint of the ash at least, as oscillators such as the
+// toad keep the same population while their phases fall apart
+const FOOTPRINT_GENERATIONS: usize = 6;
+// Name of objects that do not settle on their own, such as two colliding objects that were
+// too close to be told apart
+const UNSTABLE: &str = "zz_UNSTABLE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Unstable,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::StillLife => write!(f, "still-life"),
+            Self::Oscillator => write!(f, "oscillator"),
+            Self::Spaceship => write!(f, "spaceship"),
+            Self::Unstable => write!(f, "unstable"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tally {
+    kind: Kind,
+    count: u64,
+    // First soup the object was seen in, so it can be looked at again
+    seed: Seed,
+}
+
+#[derive(Debug)]
+pub struct Census {
+    rule: Rule,
+    // Options that make the soups, written at the top of the report. Together with --seed they
+    // replay a soup, which runs on the infinite plane like in the census
+    description: String,
+    objects: HashMap<String, Tally>,
+    soups: u64,
+    // Soups whose population was still changing after MAX_GENERATIONS
+    unsettled: u64,
+}
+
+impl Census {
+    // Soups run on the sparse engine, which can not run B0 rules
+    pub fn new(rule: Rule, description: String) -> Result<Self, SparseError> {
+        if rule.is_born(0) {
+            return Err(SparseError::BirthOnZero(rule));
+        }
+        Ok(Self {
+            rule,
+            description,
+            objects: HashMap::new(),
+            soups: 0,
+            unsettled: 0,
+        })
+    }
+
+    // Runs a soup until it settles and tallies the objects it leaves behind
+    pub fn add_soup(&mut self, seed: &Seed, cells: impl Iterator<Item = Coord>) {
+        self.soups += 1;
+        // The rule was checked by Census::new
+        let mut life = SparseLife::from_iter(self.rule, cells).unwrap();
+        let Some(period) = settle(&mut life) else {
+            self.unsettled += 1;
+            return;
+        };
+        // Cells of every phase keep the parts of an oscillator together
+        let ash: HashSet<Coord> = life.live_cells().into_iter().collect();
+        let mut footprint = ash.clone();
+        let mut later = life.clone();
+        for _ in 1..period.max(FOOTPRINT_GENERATIONS) {
+            later.step();
+            footprint.extend(later.live_cells());
+        }
+        for object in split(&footprint) {
+            let cells = object.into_iter().filter(|coord| ash.contains(coord));
+            let (code, kind) = self.classify(cells.collect());
+            let tally = self.objects.entry(code).or_insert_with(|| Tally {
+                kind,
+                count: 0,
+                seed: seed.clone(),
+            });
+            tally.count += 1;
+        }
+    }
+
+    // Runs an object on its own to find its apgcode
+    fn classify(&self, cells: Vec<Coord>) -> (String, Kind) {
+        match apgcode::identify(self.rule, cells, MAX_PERIOD as u64) {
+            Some((code, cycle)) => {
+                let kind = match cycle.period {
+                    _ if cycle.is_spaceship() => Kind::Spaceship,
+                    1 => Kind::StillLife,
+                    _ => Kind::Oscillator,
+                };
+                (code, kind)
+            }
+            None => (UNSTABLE.to_string(), Kind::Unstable),
+        }
+    }
+
+    // Objects from the most to the least common, with the number of soups in the header
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut objects: Vec<(&String, &Tally)> = self.objects.iter().collect();
+        objects.sort_by(|(a, a_tally), (b, b_tally)| {
+            b_tally.count.cmp(&a_tally.count).then_with(|| a.cmp(b))
+        });
+        writeln!(out, "# rule {} | {}", self.rule, self.description)?;
+        writeln!(
+            out,
+            "# replay a soup with --seed <first-seed> --rule {} --engine sparse {}",
+            self.rule, self.description
+        )?;
+        writeln!(
+            out,
+            "# {} soups, {} did not settle",
+            self.soups, self.unsettled
+        )?;
+        writeln!(out, "# apgcode count kind first-seed")?;
+        for (code, tally) in objects {
+            writeln!(
+                out,
+                "{} {} {} {}",
+                code, tally.count, tally.kind, tally.seed
+            )?;
+        }
+        out.flush()
+    }
+}
+
+// Steps until the population repeats, returning its period, or None if it never does
+fn settle(life: &mut SparseLife) -> Option<usize> {
+    let mut populations = vec![life.live_cells().len()];
+    for _ in 0..MAX_GENERATIONS {
+        life.step();
+        populations.push(life.live_cells().len());
+        let settled = |period: usize| {
+            populations.len() > SETTLED_FOR + period
+                && populations
+                    .iter()
+                    .rev()
+                    .zip(populations.iter().rev().skip(period))
+                    .take(SETTLED_FOR)
+                    .all(|(now, before)| now == before)
+        };
+        if let Some(period) = (1..=MAX_PERIOD).find(|&period| settled(period)) {
+            return Some(period);
+        }
+    }
+    None
+}
+
+// Groups of cells where every cell is at most OBJECT_SPACING away from another one of its group
+pub fn split(cells: &HashSet<Coord>) -> Vec<Vec<Coord>> {
+    let mut seen: HashSet<Coord> = HashSet::new();
+    let mut objects = Vec::new();
+    for start in cells {
+        if !seen.insert(start.clone()) {
+            continue;
+        }
+        let mut object = vec![start.clone()];
+        let mut i = 0;
+        while i < object.len() {
+            let coord = object[i].clone();
+            i += 1;
+            for dy in -OBJECT_SPACING..=OBJECT_SPACING {
+                for dx in -OBJECT_SPACING..=OBJECT_SPACING {
+                    let near = coord.step(dx, dy);
+                    if cells.contains(&near) && seen.insert(near.clone()) {
+                        object.push(near);
+                    }
+                }
+            }
+        }
+        objects.push(object);
+    }
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(cells: &[(i32, i32)]) -> Vec<Coord> {
+        cells.iter().map(|&(x, y)| Coord { x, y }).collect()
+    }
+
+    fn sizes(objects: Vec<Vec<Coord>>) -> Vec<usize> {
+        let mut sizes: Vec<usize> = objects.iter().map(|object| object.len()).collect();
+        sizes.sort_unstable();
+        sizes
+    }
+
+    #[test]
+    fn split_joins_diagonal_neighbors() {
+        let diagonal = cells(&[(0, 0), (1, 1), (2, 2), (4, 4)]);
+        assert_eq!(sizes(split(&diagonal.into_iter().collect())), [1, 3]);
+    }
+
+    #[test]
+    fn split_separates_objects_one_cell_apart() {
+        // A block and a blinker with an empty column between them
+        let apart = cells(&[(0, 0), (1, 0), (0, 1), (1, 1), (3, 0), (3, 1), (3, 2)]);
+        assert_eq!(sizes(split(&apart.into_iter().collect())), [3, 4]);
+        // Touching objects can not be told apart
+        let touching = cells(&[(0, 0), (1, 0), (0, 1), (1, 1), (2, 2), (3, 2), (4, 2)]);
+        assert_eq!(sizes(split(&touching.into_iter().collect())), [7]);
+    }
+
+    #[test]
+    fn settles_once_the_population_repeats() {
+        let rule = Rule::default();
+        // A blinker keeps 3 cells in both phases
+        let blinker = cells(&[(0, 0), (1, 0), (2, 0)]);
+        let mut life = SparseLife::from_iter(rule, blinker.into_iter()).unwrap();
+        assert_eq!(settle(&mut life), Some(1));
+        // A beacon goes between 8 and 6 cells
+        let beacon = cells(&[
+            (0, 0),
+            (1, 0),
+            (0, 1),
+            (1, 1),
+            (2, 2),
+            (3, 2),
+            (2, 3),
+            (3, 3),
+        ]);
+        let mut life = SparseLife::from_iter(rule, beacon.into_iter()).unwrap();
+        assert_eq!(settle(&mut life), Some(2));
+    }
+
+    #[test]
+    fn tallies_the_objects_a_soup_leaves_behind() {
+        let mut census = Census::new(Rule::default(), "--soup-size 16x16".to_string()).unwrap();
+        // An L tromino that turns into a block, and a blinker far away from it
+        let soup = cells(&[(0, 0), (1, 0), (0, 1), (10, 0), (10, 1), (10, 2)]);
+        census.add_soup(&Seed::Number(7), soup.into_iter());
+        let count = |code: &str| census.objects.get(code).map(|tally| tally.count);
+        assert_eq!(count("xs4_33"), Some(1));
+        assert_eq!(count("xp2_7"), Some(1));
+        assert_eq!(census.objects.len(), 2);
+        assert_eq!(census.objects["xp2_7"].kind, Kind::Oscillator);
+
+        let mut report = Vec::new();
+        census.write(&mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.contains("\nxp2_7 1 oscillator 7\n"));
+        assert!(report.contains("\nxs4_33 1 still-life 7\n"));
+        assert!(report.contains("# 1 soups, 0 did not settle\n"));
+        assert!(report.contains(
+            "# replay a soup with --seed <first-seed> --rule B3/S23 --engine sparse --soup-size 16x16\n"
+        ));
+    }
+}
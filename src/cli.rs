@@ -0,0 +1,452 @@
+// Dependency free command line parsing
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::palette::{ColorMode, Colors};
+use crate::pattern::Format;
+use crate::render::Renderer;
+use crate::rng::{Generator, Seed};
+use crate::rule::Rule;
+use crate::soup::Symmetry;
+use crate::stats::StatsFormat;
+use crate::{Coord, Topology};
+
+pub const USAGE: &str = "\
+Usage: gol [OPTIONS]
+
+Options:
+  --width N          Width of the board in cells (default: 15, or enough to fit the pattern)
+  --height N         Height of the board in cells (default: 15, or enough to fit the pattern)
+  --seed SEED        Seed of the random soup, a number or any text (default: current time)
+  --rng RNG          xoshiro256, pcg32 or lcg, generator of the random soup (default: xoshiro256)
+  --density F        Fraction of the soup's cells that are alive, 0 to 1 (default: 0.44)
+  --soup-size WxH    Size of the soup, centred on the board (default: the whole board)
+  --symmetry S       C1, C2_1, C2_2, C2_4, C4_1, C4_4, D2_+1, D2_+2, D2_x, D4_+1, D4_+2,
+                     D4_+4, D4_x1, D4_x4, D8_1 or D8_4, mirrors and rotates the soup like
+                     apgsearch, shrinking it by a cell where needed (default: C1)
+  --delay-ms N       Milliseconds between two generations (default: 100)
+  --rule RULE        Rulestring such as B3/S23 or 23/3 (default: the pattern's rule or B3/S23)
+  --pattern FILE     Start from a pattern file instead of a random soup, the format (RLE,
+                     plaintext, Life 1.05, Life 1.06 or macrocell) is detected from its contents
+  --apgcode CODE     Start from the object named by an apgcode, such as xq4_153 for a glider
+  --offset X,Y       Position of the top left corner of the pattern or soup (default: centred)
+  --output FILE      Save the board once the simulation stops
+  --output-format F  rle, cells, life105, life106 or mc (default: from the extension of the
+                     output file, .cells .txt .lif .life .mc or RLE otherwise)
+  --generations N    Stop after N generations (default: run forever)
+  --until-stable     Stop once the board is static or periodic and print a summary. Without
+                     animation, gives up after --generations (default: 10000)
+  --batch            Run as fast as possible without animating, then print the final board
+                     (plaintext, or --output-format), generations, population and elapsed time
+  --expect-population N
+                     Exit with status 1 unless the final population is N
+  --census N         Run N soups of --soup-size (default: 16x16) on the infinite plane until
+                     they settle, then count the objects they leave behind by apgcode
+  --census-report FILE
+                     Write the census to FILE instead of stdout
+  --stats FILE       Write population, births, deaths, bounding box and density of every
+                     generation to FILE, or to stdout without animating the board for -
+  --stats-format F   csv or jsonl (default: jsonl for .jsonl and .json files, csv otherwise)
+  --engine ENGINE    dense, bitgrid, sparse or hashlife (default: hashlife for macrocell
+                     patterns, dense otherwise)
+  --topology TOPO    torus or bounded, for the dense engines (default: torus)
+  --threads N        Row bands stepped in parallel by the dense engines (default: 1)
+  --memory-mb N      Node cache limit of the hashlife engine (default: unlimited)
+  --renderer R       classic, half-block (1x2 cells per character) or braille (2x4 cells per
+                     character), v switches while running (default: classic)
+  --color MODE       none, 256 or truecolor, colours cells of the dense engine by age and
+                     leaves fading trails behind dead cells (default: none)
+  --palette P        heat, ocean, forest or three hex colours BORN,OLD,TRAIL such as
+                     fff0a0,c81e1e,5a3ca0 (default: heat)
+  --help             Print this message
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Dense,
+    BitGrid,
+    Sparse,
+    HashLife,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub seed: Option<Seed>,
+    pub generator: Generator,
+    pub density: f64,
+    pub soup_size: Option<(u32, u32)>,
+    pub symmetry: Symmetry,
+    pub delay: Duration,
+    pub rule: Option<Rule>,
+    pub pattern: Option<PathBuf>,
+    pub apgcode: Option<String>,
+    pub offset: Option<Coord>,
+    pub output: Option<PathBuf>,
+    pub output_format: Option<Format>,
+    pub generations: Option<u64>,
+    pub engine: Option<Engine>,
+    pub topology: Topology,
+    pub threads: usize,
+    pub memory_limit: Option<usize>,
+    pub renderer: Renderer,
+    pub colors: Colors,
+    pub until_stable: bool,
+    pub stats: Option<PathBuf>,
+    pub stats_format: Option<StatsFormat>,
+    pub batch: bool,
+    pub expect_population: Option<usize>,
+    pub census: Option<u64>,
+    pub census_report: Option<PathBuf>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            width: None,
+            height: None,
+            seed: None,
+            generator: Generator::default(),
+            density: 0.44,
+            soup_size: None,
+            symmetry: Symmetry::default(),
+            delay: Duration::from_millis(100),
+            rule: None,
+            pattern: None,
+            apgcode: None,
+            offset: None,
+            output: None,
+            output_format: None,
+            generations: None,
+            engine: None,
+            topology: Topology::default(),
+            threads: 1,
+            memory_limit: None,
+            renderer: Renderer::default(),
+            colors: Colors::default(),
+            until_stable: false,
+            stats: None,
+            stats_format: None,
+            batch: false,
+            expect_population: None,
+            census: None,
+            census_report: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArgsError {
+    HelpRequested,
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue {
+        option: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::HelpRequested => write!(f, "help requested"),
+            Self::UnknownOption(option) => write!(f, "unknown option `{}`", option),
+            Self::MissingValue(option) => write!(f, "`{}` expects a value", option),
+            Self::InvalidValue {
+                option,
+                value,
+                reason,
+            } => write!(f, "invalid value `{}` for `{}`: {}", value, option, reason),
+        }
+    }
+}
+
+fn parse_value<T>(
+    option: &str,
+    value: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<T, ArgsError> {
+    parse(value).map_err(|reason| ArgsError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+        reason,
+    })
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|err: T::Err| err.to_string())
+}
+
+fn positive<T: std::str::FromStr + Default + PartialOrd>(value: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    let number: T = number(value)?;
+    if number > T::default() {
+        Ok(number)
+    } else {
+        Err("must be greater than zero".to_string())
+    }
+}
+
+impl Args {
+    // Accepts both `--option value` and `--option=value`, args should not include the program name
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            let (option, mut inline_value) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => {
+                    (option.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            if option == "--help" || option == "-h" {
+                return Err(ArgsError::HelpRequested);
+            }
+            // Flags take no value, options take the one after = or the next argument
+            let flag = |option: &str, inline_value: Option<String>| match inline_value {
+                Some(value) => Err(ArgsError::InvalidValue {
+                    option: option.to_string(),
+                    value,
+                    reason: "takes no value".to_string(),
+                }),
+                None => Ok(true),
+            };
+            let mut value = || {
+                inline_value
+                    .take()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ArgsError::MissingValue(option.clone()))
+            };
+            match option.as_str() {
+                "--until-stable" => parsed.until_stable = flag(&option, inline_value.take())?,
+                "--batch" => parsed.batch = flag(&option, inline_value.take())?,
+                "--width" => parsed.width = Some(parse_value(&option, &value()?, positive)?),
+                "--height" => parsed.height = Some(parse_value(&option, &value()?, positive)?),
+                "--seed" => {
+                    parsed.seed = Some(parse_value(&option, &value()?, |value| value.parse())?)
+                }
+                "--rng" => {
+                    parsed.generator = parse_value(&option, &value()?, |value| match value {
+                        "xoshiro256" => Ok(Generator::Xoshiro256),
+                        "pcg32" => Ok(Generator::Pcg32),
+                        "lcg" => Ok(Generator::Lcg),
+                        _ => Err("expected xoshiro256, pcg32 or lcg".to_string()),
+                    })?
+                }
+                "--density" => {
+                    parsed.density = parse_value(&option, &value()?, |value| {
+                        let density: f64 = number(value)?;
+                        if (0.0..=1.0).contains(&density) {
+                            Ok(density)
+                        } else {
+                            Err("must be between 0 and 1".to_string())
+                        }
+                    })?
+                }
+                "--soup-size" => {
+                    parsed.soup_size = Some(parse_value(&option, &value()?, |value| {
+                        let (width, height) = value
+                            .split_once('x')
+                            .ok_or_else(|| "expected WxH such as 16x16".to_string())?;
+                        Ok((positive(width.trim())?, positive(height.trim())?))
+                    })?)
+                }
+                "--symmetry" => {
+                    parsed.symmetry = parse_value(&option, &value()?, |value| value.parse())?
+                }
+                "--delay-ms" => {
+                    parsed.delay = Duration::from_millis(parse_value(&option, &value()?, number)?)
+                }
+                "--rule" => {
+                    parsed.rule = Some(parse_value(&option, &value()?, |value| {
+                        value
+                            .parse()
+                            .map_err(|err: crate::rule::RuleParseError| err.to_string())
+                    })?)
+                }
+                "--pattern" => parsed.pattern = Some(PathBuf::from(value()?)),
+                "--apgcode" => parsed.apgcode = Some(value()?),
+                "--output" => parsed.output = Some(PathBuf::from(value()?)),
+                "--output-format" => {
+                    parsed.output_format =
+                        Some(parse_value(&option, &value()?, |value| match value {
+                            "rle" => Ok(Format::Rle),
+                            "cells" => Ok(Format::Plaintext),
+                            "life105" => Ok(Format::Life105),
+                            "life106" => Ok(Format::Life106),
+                            "mc" => Ok(Format::Macrocell),
+                            _ => Err("expected rle, cells, life105, life106 or mc".to_string()),
+                        })?)
+                }
+                "--offset" => {
+                    parsed.offset = Some(parse_value(&option, &value()?, |value| {
+                        let (x, y) = value
+                            .split_once(',')
+                            .ok_or_else(|| "expected X,Y such as 10,-4".to_string())?;
+                        Ok(Coord {
+                            x: number(x.trim())?,
+                            y: number(y.trim())?,
+                        })
+                    })?)
+                }
+                "--generations" => {
+                    parsed.generations = Some(parse_value(&option, &value()?, number)?)
+                }
+                "--engine" => {
+                    parsed.engine = Some(parse_value(&option, &value()?, |value| match value {
+                        "dense" => Ok(Engine::Dense),
+                        "bitgrid" => Ok(Engine::BitGrid),
+                        "sparse" => Ok(Engine::Sparse),
+                        "hashlife" => Ok(Engine::HashLife),
+                        _ => Err("expected dense, bitgrid, sparse or hashlife".to_string()),
+                    })?)
+                }
+                "--topology" => {
+                    parsed.topology = parse_value(&option, &value()?, |value| match value {
+                        "torus" => Ok(Topology::Torus),
+                        "bounded" => Ok(Topology::Bounded),
+                        _ => Err("expected torus or bounded".to_string()),
+                    })?
+                }
+                "--threads" => parsed.threads = parse_value(&option, &value()?, positive)?,
+                "--memory-mb" => {
+                    let megabytes: usize = parse_value(&option, &value()?, positive)?;
+                    parsed.memory_limit = Some(megabytes.saturating_mul(1 << 20));
+                }
+                "--renderer" => {
+                    parsed.renderer = parse_value(&option, &value()?, |value| match value {
+                        "classic" => Ok(Renderer::Classic),
+                        "half-block" => Ok(Renderer::HalfBlock),
+                        "braille" => Ok(Renderer::Braille),
+                        _ => Err("expected classic, half-block or braille".to_string()),
+                    })?
+                }
+                "--color" => {
+                    parsed.colors.mode = parse_value(&option, &value()?, |value| match value {
+                        "none" => Ok(ColorMode::Monochrome),
+                        "256" => Ok(ColorMode::Ansi256),
+                        "truecolor" => Ok(ColorMode::TrueColor),
+                        _ => Err("expected none, 256 or truecolor".to_string()),
+                    })?
+                }
+                "--palette" => {
+                    parsed.colors.palette = parse_value(&option, &value()?, |value| value.parse())?
+                }
+                "--stats" => parsed.stats = Some(PathBuf::from(value()?)),
+                "--stats-format" => {
+                    parsed.stats_format =
+                        Some(parse_value(&option, &value()?, |value| match value {
+                            "csv" => Ok(StatsFormat::Csv),
+                            "jsonl" => Ok(StatsFormat::JsonLines),
+                            _ => Err("expected csv or jsonl".to_string()),
+                        })?)
+                }
+                "--expect-population" => {
+                    parsed.expect_population = Some(parse_value(&option, &value()?, number)?)
+                }
+                "--census" => parsed.census = Some(parse_value(&option, &value()?, positive)?),
+                "--census-report" => parsed.census_report = Some(PathBuf::from(value()?)),
+                _ => return Err(ArgsError::UnknownOption(option)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_values_after_a_space_or_an_equals_sign() {
+        let args = parse(&[
+            "--width",
+            "40",
+            "--height=20",
+            "--seed",
+            "hello",
+            "--rule=B36/S23",
+        ])
+        .unwrap();
+        assert_eq!((args.width, args.height), (Some(40), Some(20)));
+        assert_eq!(args.seed, Some(Seed::Text("hello".to_string())));
+        assert_eq!(args.rule, Some("B36/S23".parse().unwrap()));
+        let args = parse(&["--offset=-3,4", "--soup-size", "16x8", "--engine=sparse"]).unwrap();
+        assert_eq!(args.offset, Some(Coord { x: -3, y: 4 }));
+        assert_eq!(args.soup_size, Some((16, 8)));
+        assert_eq!(args.engine, Some(Engine::Sparse));
+    }
+
+    #[test]
+    fn parses_flags() {
+        let args = parse(&["--batch", "--until-stable"]).unwrap();
+        assert!(args.batch && args.until_stable);
+        let args = parse(&[]).unwrap();
+        assert!(!args.batch && !args.until_stable);
+        assert_eq!(
+            parse(&["--batch=yes"]).unwrap_err(),
+            ArgsError::InvalidValue {
+                option: "--batch".to_string(),
+                value: "yes".to_string(),
+                reason: "takes no value".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_values() {
+        assert_eq!(
+            parse(&["--density"]).unwrap_err(),
+            ArgsError::MissingValue("--density".to_string())
+        );
+        assert_eq!(
+            parse(&["--width", "0"]).unwrap_err(),
+            ArgsError::InvalidValue {
+                option: "--width".to_string(),
+                value: "0".to_string(),
+                reason: "must be greater than zero".to_string()
+            }
+        );
+        assert!(matches!(
+            parse(&["--density=1.5"]),
+            Err(ArgsError::InvalidValue { option, .. }) if option == "--density"
+        ));
+        assert!(matches!(
+            parse(&["--engine", "quantum"]),
+            Err(ArgsError::InvalidValue { option, .. }) if option == "--engine"
+        ));
+        assert!(matches!(
+            parse(&["--offset", "3"]),
+            Err(ArgsError::InvalidValue { option, .. }) if option == "--offset"
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert_eq!(
+            parse(&["--wdith", "40"]).unwrap_err(),
+            ArgsError::UnknownOption("--wdith".to_string())
+        );
+        assert_eq!(
+            parse(&["--wdith=40"]).unwrap_err(),
+            ArgsError::UnknownOption("--wdith".to_string())
+        );
+        assert_eq!(
+            parse(&["40"]).unwrap_err(),
+            ArgsError::UnknownOption("40".to_string())
+        );
+        assert_eq!(parse(&["-h"]).unwrap_err(), ArgsError::HelpRequested);
+    }
+}
@@ -0,0 +1,295 @@
+// Notices when the board settles down. Every generation is remembered regardless of where it
+// sits, and a board that matches an earlier one is static or oscillating if it came back in
+// place, or a spaceship if it came back somewhere else.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::{Coord, Life};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    // 1 for still lifes and boards that died out
+    pub period: u64,
+    // First generation of the cycle
+    pub start: u64,
+    // How far the pattern moves every period, (0, 0) unless it is a spaceship
+    pub displacement: (i32, i32),
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Cycle {
+    pub fn is_spaceship(&self) -> bool {
+        self.displacement != (0, 0)
+    }
+
+    // Speed in cells per generation and direction, such as c/4 diagonal, c/2 orthogonal or
+    // (2,1)c/6 oblique. Speeds are reduced, so a ship moving 2 cells in 4 generations is c/2
+    pub fn velocity(&self) -> String {
+        let (dx, dy) = (
+            self.displacement.0.unsigned_abs() as u64,
+            self.displacement.1.unsigned_abs() as u64,
+        );
+        let (fast, slow) = (dx.max(dy), dx.min(dy));
+        if slow != 0 && slow != fast {
+            return format!("({},{})c/{} oblique", fast, slow, self.period);
+        }
+        let direction = if slow == 0 { "orthogonal" } else { "diagonal" };
+        let divisor = gcd(fast, self.period);
+        match fast / divisor {
+            1 => format!("c/{} {}", self.period / divisor, direction),
+            speed => format!("{}c/{} {}", speed, self.period / divisor, direction),
+        }
+    }
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.period {
+            _ if self.is_spaceship() => write!(
+                f,
+                "period {} spaceship moving {} since generation {}",
+                self.period,
+                self.velocity(),
+                self.start
+            ),
+            1 => write!(f, "static since generation {}", self.start),
+            period => write!(f, "period {} since generation {}", period, self.start),
+        }
+    }
+}
+
+// Where to start counting an axis so the cells on it sit right after the widest empty gap,
+// which keeps a pattern on a torus from being split across the edge. Ties give several starts,
+// and a pattern covering the whole axis is left in place
+fn axis_starts(values: impl Iterator<Item = i32>, size: Option<u32>) -> Vec<i32> {
+    let mut occupied: Vec<i32> = values.collect();
+    occupied.sort_unstable();
+    occupied.dedup();
+    let (Some(&last), Some(size)) = (occupied.last(), size) else {
+        return vec![occupied.first().copied().unwrap_or(0)];
+    };
+    let gaps: Vec<(i64, i32)> = occupied
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            let previous = match i {
+                0 => last as i64 - size as i64,
+                i => occupied[i - 1] as i64,
+            };
+            (value as i64 - previous - 1, value)
+        })
+        .collect();
+    let widest = gaps.iter().map(|&(gap, _)| gap).max().unwrap();
+    if widest == 0 {
+        return vec![0];
+    }
+    gaps.into_iter()
+        .filter(|&(gap, _)| gap == widest)
+        .map(|(_, value)| value)
+        .collect()
+}
+
+// Position of a value counted from start, on an axis that wraps around when it has a size.
+// Computed in i64 as the start can be i32::MIN, and a pattern spanning more than i32::MAX
+// cells on the infinite plane is squashed against the far edge
+fn shift(value: i32, start: i32, size: Option<u32>) -> i32 {
+    let difference = value as i64 - start as i64;
+    match size {
+        Some(size) => difference.rem_euclid(size as i64) as i32,
+        None => difference.min(i32::MAX as i64) as i32,
+    }
+}
+
+// Cells moved so the pattern starts at (0, 0), sorted by row, with the offset they were moved
+// by. A pattern on a torus is unwrapped first, and the smallest result wins ties
+pub fn normalise(cells: &[Coord], torus: Option<(u32, u32)>) -> (Vec<Coord>, Coord) {
+    let starts_x = axis_starts(cells.iter().map(|coord| coord.x), torus.map(|size| size.0));
+    let starts_y = axis_starts(cells.iter().map(|coord| coord.y), torus.map(|size| size.1));
+    let mut best: Option<(Vec<Coord>, Coord)> = None;
+    for &y in &starts_y {
+        for &x in &starts_x {
+            let offset = Coord { x, y };
+            let mut moved: Vec<Coord> = cells
+                .iter()
+                .map(|coord| Coord {
+                    x: shift(coord.x, x, torus.map(|size| size.0)),
+                    y: shift(coord.y, y, torus.map(|size| size.1)),
+                })
+                .collect();
+            moved.sort_by_key(|coord| (coord.y, coord.x));
+            let key = |cells: &[Coord]| -> Vec<(i32, i32)> {
+                cells.iter().map(|coord| (coord.y, coord.x)).collect()
+            };
+            if best
+                .as_ref()
+                .is_none_or(|(best, _)| key(&moved) < key(best))
+            {
+                best = Some((moved, offset));
+            }
+        }
+    }
+    best.unwrap()
+}
+
+// Difference between two positions along an axis, the short way round on a torus
+fn distance(from: i32, to: i32, size: Option<u32>) -> i32 {
+    let difference = to as i64 - from as i64;
+    match size {
+        Some(size) => {
+            let size = size as i64;
+            let difference = difference.rem_euclid(size);
+            (if difference > size / 2 {
+                difference - size
+            } else {
+                difference
+            }) as i32
+        }
+        None => difference as i32,
+    }
+}
+
+#[derive(Debug)]
+pub struct CycleDetector {
+    // Number of generations remembered, longer cycles go unnoticed
+    capacity: usize,
+    // Generation, position and normalised cells of every remembered board, by hash
+    boards: HashMap<u64, (u64, Coord, Vec<Coord>)>,
+    // Hashes in the order they were recorded, so the oldest can be forgotten
+    hashes: VecDeque<u64>,
+}
+
+impl CycleDetector {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            boards: HashMap::new(),
+            hashes: VecDeque::new(),
+        }
+    }
+
+    // Forgets every board, for when the board is changed by hand
+    pub fn clear(&mut self) {
+        self.boards.clear();
+        self.hashes.clear();
+    }
+
+    // Records the board of a generation, returning the cycle if an earlier generation in the
+    // history looked exactly the same, possibly somewhere else
+    pub fn record(&mut self, generation: u64, life: &dyn Life) -> Option<Cycle> {
+        let torus = life.torus_size();
+        let (cells, offset) = normalise(&life.live_cells(), torus);
+        let mut hasher = DefaultHasher::new();
+        cells.hash(&mut hasher);
+        let hash = hasher.finish();
+        match self.boards.get(&hash) {
+            Some((start, earlier, earlier_cells)) if *earlier_cells == cells => {
+                return Some(Cycle {
+                    period: generation - start,
+                    start: *start,
+                    displacement: (
+                        distance(earlier.x, offset.x, torus.map(|size| size.0)),
+                        distance(earlier.y, offset.y, torus.map(|size| size.1)),
+                    ),
+                })
+            }
+            // A different board with the same hash keeps its place, this one is not remembered
+            Some(_) => return None,
+            None => {}
+        }
+        if self.hashes.len() == self.capacity {
+            let oldest = self.hashes.pop_front().unwrap();
+            self.boards.remove(&oldest);
+        }
+        self.hashes.push_back(hash);
+        self.boards.insert(hash, (generation, offset, cells));
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+    use crate::sparse::SparseLife;
+    use crate::GOL;
+
+    fn cells(cells: &[(i32, i32)]) -> impl Iterator<Item = Coord> + '_ {
+        cells.iter().map(|&(x, y)| Coord { x, y })
+    }
+
+    // First cycle found within 100 generations
+    fn first_cycle(life: &mut dyn Life) -> Option<Cycle> {
+        let mut detector = CycleDetector::new(100);
+        (0..100).find_map(|generation| {
+            if generation > 0 {
+                life.step();
+            }
+            detector.record(generation, life)
+        })
+    }
+
+    #[test]
+    fn finds_oscillators() {
+        let mut blinker =
+            SparseLife::from_iter(Rule::default(), cells(&[(0, 0), (1, 0), (2, 0)])).unwrap();
+        let cycle = first_cycle(&mut blinker).unwrap();
+        assert_eq!(
+            (cycle.period, cycle.start, cycle.displacement),
+            (2, 0, (0, 0))
+        );
+    }
+
+    #[test]
+    fn finds_spaceships() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut sparse = SparseLife::from_iter(Rule::default(), cells(&glider)).unwrap();
+        let cycle = first_cycle(&mut sparse).unwrap();
+        assert_eq!((cycle.period, cycle.displacement), (4, (1, 1)));
+        assert_eq!(cycle.velocity(), "c/4 diagonal");
+        // Across the edge of a torus, the short way round
+        let mut torus = GOL::from_iter(8, 8, cells(&glider).map(|coord| coord.step(5, 5)));
+        let cycle = first_cycle(&mut torus).unwrap();
+        assert_eq!((cycle.period, cycle.displacement), (4, (1, 1)));
+    }
+
+    #[test]
+    fn normalises_patterns_at_the_edges_of_the_coordinate_space() {
+        let block = [
+            (i32::MIN, 0),
+            (i32::MIN + 1, 0),
+            (i32::MIN, 1),
+            (i32::MIN + 1, 1),
+        ];
+        let (moved, offset) = normalise(&cells(&block).collect::<Vec<_>>(), None);
+        assert_eq!(offset, Coord { x: i32::MIN, y: 0 });
+        assert_eq!(
+            moved,
+            cells(&[(0, 0), (1, 0), (0, 1), (1, 1)]).collect::<Vec<_>>()
+        );
+        // Cells further apart than i32::MAX are kept in the coordinate space
+        let (moved, _) = normalise(
+            &cells(&[(i32::MIN, 0), (i32::MAX, 0)]).collect::<Vec<_>>(),
+            None,
+        );
+        assert_eq!(moved, cells(&[(0, 0), (i32::MAX, 0)]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn only_matches_the_same_board() {
+        let mut detector = CycleDetector::new(10);
+        let block = GOL::from_iter(10, 10, cells(&[(1, 1), (2, 1), (1, 2), (2, 2)]));
+        let blinker = GOL::from_iter(10, 10, cells(&[(1, 1), (2, 1), (3, 1)]));
+        assert_eq!(detector.record(0, &block), None);
+        assert_eq!(detector.record(1, &blinker), None);
+        assert_eq!(detector.record(2, &block).map(|cycle| cycle.start), Some(0));
+    }
+}
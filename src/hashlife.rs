@@ -0,0 +1,782 @@
+// HashLife, see https://en.wikipedia.org/wiki/Hashlife
+// The plane is a quadtree of canonicalised nodes, and the result of advancing the centre of every
+// node is memoised, so repetitive patterns can jump ahead by huge powers of two at once.
+// Like the sparse engine it simulates an infinite plane, so B0 rules are not supported.
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::rule::Rule;
+#[cfg(test)]
+use crate::GOL;
+use crate::{Coord, Life};
+
+pub type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+// Rough cost of a node: the node itself plus its entries in the canonical and result tables
+const BYTES_PER_NODE: usize = 96;
+
+#[derive(Debug, Clone)]
+struct Node {
+    level: u8,
+    // Quadrants, unused for level 0 nodes which are single cells
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+    // Saturates instead of overflowing, only emptiness has to be exact
+    population: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HashLifeError {
+    // Empty nodes are skipped as they stay empty, which is not true when cells are born on 0
+    BirthOnZero(Rule),
+}
+
+impl fmt::Display for HashLifeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BirthOnZero(rule) => write!(
+                f,
+                "rule {} can not run on the hashlife engine, B0 rules would fill the infinite plane",
+                rule
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HashLifeError {}
+
+#[derive(Debug)]
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    canonical: HashMap<[NodeId; 4], NodeId>,
+    // Centre of a node advanced by 2^j generations, keyed by (node, j)
+    results: HashMap<(NodeId, u8), NodeId>,
+    // Empty node of every level built so far
+    empty: Vec<NodeId>,
+    root: NodeId,
+    // Coordinate of the top left cell of root
+    origin_x: i128,
+    origin_y: i128,
+    max_nodes: usize,
+    // Part of the plane that gets drawn
+    view_origin: Coord,
+    view_width: u32,
+    view_height: u32,
+}
+
+impl HashLife {
+    pub fn new(rule: Rule) -> Result<Self, HashLifeError> {
+        if rule.is_born(0) {
+            return Err(HashLifeError::BirthOnZero(rule));
+        }
+        let leaf = |population| Node {
+            level: 0,
+            nw: DEAD,
+            ne: DEAD,
+            sw: DEAD,
+            se: DEAD,
+            population,
+        };
+        let mut hashlife = Self {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            canonical: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            origin_x: 0,
+            origin_y: 0,
+            max_nodes: usize::MAX,
+            view_origin: Coord { x: 0, y: 0 },
+            view_width: 15,
+            view_height: 15,
+        };
+        hashlife.root = hashlife.empty_node(3);
+        hashlife.origin_x = -4;
+        hashlife.origin_y = -4;
+        Ok(hashlife)
+    }
+
+    pub fn from_iter(
+        rule: Rule,
+        live_coords: impl Iterator<Item = Coord>,
+    ) -> Result<Self, HashLifeError> {
+        let mut hashlife = Self::new(rule)?;
+        let mut cells: Vec<(i128, i128)> = live_coords
+            .map(|coord| (coord.x as i128, coord.y as i128))
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+        if let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (
+            cells.iter().map(|cell| cell.0).min(),
+            cells.iter().map(|cell| cell.0).max(),
+            cells.iter().map(|cell| cell.1).min(),
+            cells.iter().map(|cell| cell.1).max(),
+        ) {
+            let size = (max_x - min_x).max(max_y - min_y) + 1;
+            let mut level = 3;
+            while (1i128 << level) < size {
+                level += 1;
+            }
+            hashlife.origin_x = min_x;
+            hashlife.origin_y = min_y;
+            hashlife.root = hashlife.build(level, min_x, min_y, &mut cells);
+        }
+        Ok(hashlife)
+    }
+
+    #[cfg(test)]
+    pub fn from_gol(gol: &GOL) -> Self {
+        Self::from_iter(gol.rule, gol.live_cells().into_iter()).unwrap()
+    }
+
+    // Copies the cells inside of the width x height board back into a dense grid
+    #[cfg(test)]
+    pub fn to_gol(&self, width: u32, height: u32) -> GOL {
+        let mut cells = Vec::new();
+        self.collect_cells(
+            self.root,
+            self.origin_x,
+            self.origin_y,
+            (0, 0, width as i128, height as i128),
+            &mut cells,
+        );
+        GOL::from_iter(width, height, cells.into_iter()).with_rule(self.rule)
+    }
+
+    // Caps the size of the node cache, nodes unreachable from the current pattern are collected
+    // whenever the cache grows past the limit between two steps
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.max_nodes = (bytes / BYTES_PER_NODE).max(1024);
+        self
+    }
+
+    pub fn with_viewport(mut self, origin: Coord, width: u32, height: u32) -> Self {
+        self.view_origin = origin;
+        self.view_width = width;
+        self.view_height = height;
+        self
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    // Memoised results depend on the rule, so they are dropped
+    pub fn set_rule(&mut self, rule: Rule) -> Result<(), HashLifeError> {
+        if rule.is_born(0) {
+            return Err(HashLifeError::BirthOnZero(rule));
+        }
+        self.rule = rule;
+        self.results.clear();
+        Ok(())
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    // Replaces the pattern by the tree under root, with its top left corner at (x, y)
+    pub fn set_root(&mut self, root: NodeId, x: i128, y: i128) {
+        self.root = root;
+        self.origin_x = x;
+        self.origin_y = y;
+        while self.level(self.root) < 3 {
+            self.expand();
+        }
+    }
+
+    pub fn translate(&mut self, dx: i128, dy: i128) {
+        self.origin_x += dx;
+        self.origin_y += dy;
+    }
+
+    pub fn node_population(&self, id: NodeId) -> u64 {
+        self.nodes[id as usize].population
+    }
+
+    // Node of the given level holding the live cells, relative to its top left corner
+    pub fn build_node(&mut self, level: u8, cells: &[Coord]) -> NodeId {
+        let mut cells: Vec<(i128, i128)> = cells
+            .iter()
+            .map(|coord| (coord.x as i128, coord.y as i128))
+            .collect();
+        self.build(level, 0, 0, &mut cells)
+    }
+
+    // Live cells of a node, relative to its top left corner
+    pub fn node_cells(&self, id: NodeId) -> Vec<Coord> {
+        let mut cells = Vec::new();
+        let size = 1i128 << self.level(id);
+        self.collect_cells(id, 0, 0, (0, 0, size, size), &mut cells);
+        cells
+    }
+
+    // Smallest (min x, min y, max x, max y) containing every live cell, without visiting them all
+    pub fn bounding_box(&self) -> Option<(i128, i128, i128, i128)> {
+        // Quadrants on the low and high side of each axis
+        const WEST: [usize; 2] = [0, 2];
+        const EAST: [usize; 2] = [1, 3];
+        const NORTH: [usize; 2] = [0, 1];
+        const SOUTH: [usize; 2] = [2, 3];
+        let edge = |first: [usize; 2], second: [usize; 2], lowest: bool| {
+            self.edge(self.root, first, second, lowest, &mut HashMap::new())
+        };
+        let min_x = edge(WEST, EAST, true)?;
+        let max_x = edge(EAST, WEST, false)?;
+        let min_y = edge(NORTH, SOUTH, true)?;
+        let max_y = edge(SOUTH, NORTH, false)?;
+        Some((
+            self.origin_x + min_x,
+            self.origin_y + min_y,
+            self.origin_x + max_x,
+            self.origin_y + max_y,
+        ))
+    }
+
+    // Offset along one axis of the outermost live cell of a node, searching the quadrants in
+    // first before those in second. Memoised as canonical nodes repeat all over large patterns
+    fn edge(
+        &self,
+        id: NodeId,
+        first: [usize; 2],
+        second: [usize; 2],
+        lowest: bool,
+        memo: &mut HashMap<NodeId, Option<i128>>,
+    ) -> Option<i128> {
+        let node = &self.nodes[id as usize];
+        if node.population == 0 {
+            return None;
+        }
+        if node.level == 0 {
+            return Some(0);
+        }
+        if let Some(&offset) = memo.get(&id) {
+            return offset;
+        }
+        let half = 1i128 << (node.level - 1);
+        let children = self.children(id);
+        let outermost = |offsets: [Option<i128>; 2]| {
+            let offsets = offsets.into_iter().flatten();
+            if lowest {
+                offsets.min()
+            } else {
+                offsets.max()
+            }
+        };
+        // The first quadrants are on the low side when searching for the lowest offset
+        let (first_shift, second_shift) = if lowest { (0, half) } else { (half, 0) };
+        let offset = outermost(first.map(|q| self.edge(children[q], first, second, lowest, memo)))
+            .map(|offset| offset + first_shift)
+            .or_else(|| {
+                outermost(second.map(|q| self.edge(children[q], first, second, lowest, memo)))
+                    .map(|offset| offset + second_shift)
+            });
+        memo.insert(id, offset);
+        offset
+    }
+
+    // Jumps ahead by the given number of generations, one power of two at a time
+    pub fn advance(&mut self, generations: u64) {
+        for j in 0..u64::BITS as u8 {
+            if generations & (1 << j) == 0 {
+                continue;
+            }
+            // Keep the pattern within the centre of the centre of root, so that
+            // nothing can escape the part of the plane returned by successor
+            while self.level(self.root) < j + 3 || !self.is_padded(self.root) {
+                self.expand();
+            }
+            let level = self.level(self.root);
+            self.root = self.successor(self.root, j);
+            let shift = 1i128 << (level - 2);
+            self.origin_x += shift;
+            self.origin_y += shift;
+            self.shrink();
+            if self.nodes.len() > self.max_nodes {
+                self.collect_garbage();
+            }
+        }
+    }
+
+    // Drops every node and memoised result that the current pattern does not depend on
+    pub fn collect_garbage(&mut self) {
+        let mut reachable = vec![false; self.nodes.len()];
+        reachable[DEAD as usize] = true;
+        reachable[ALIVE as usize] = true;
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            if reachable[id as usize] {
+                continue;
+            }
+            reachable[id as usize] = true;
+            let node = &self.nodes[id as usize];
+            stack.extend([node.nw, node.ne, node.sw, node.se]);
+        }
+
+        // Children always come before their parents, so ids can be remapped in a single pass
+        let mut remap = vec![NodeId::MAX; self.nodes.len()];
+        let mut nodes = Vec::new();
+        for (id, node) in self.nodes.iter().enumerate() {
+            if !reachable[id] {
+                continue;
+            }
+            remap[id] = nodes.len() as NodeId;
+            let mut node = node.clone();
+            if node.level > 0 {
+                node.nw = remap[node.nw as usize];
+                node.ne = remap[node.ne as usize];
+                node.sw = remap[node.sw as usize];
+                node.se = remap[node.se as usize];
+            }
+            nodes.push(node);
+        }
+
+        self.canonical = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.level > 0)
+            .map(|(id, node)| ([node.nw, node.ne, node.sw, node.se], id as NodeId))
+            .collect();
+        self.results = self
+            .results
+            .iter()
+            .filter(|((id, _), result)| reachable[*id as usize] && reachable[**result as usize])
+            .map(|(&(id, j), &result)| ((remap[id as usize], j), remap[result as usize]))
+            .collect();
+        self.root = remap[self.root as usize];
+        self.nodes = nodes;
+        self.empty = vec![DEAD];
+    }
+
+    pub fn level(&self, id: NodeId) -> u8 {
+        self.nodes[id as usize].level
+    }
+
+    pub fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        if let Some(&id) = self.canonical.get(&[nw, ne, sw, se]) {
+            return id;
+        }
+        let [a, b, c, d] = [nw, ne, sw, se].map(|id| &self.nodes[id as usize]);
+        let node = Node {
+            level: a.level + 1,
+            nw,
+            ne,
+            sw,
+            se,
+            population: [b, c, d].iter().fold(a.population, |sum, node| {
+                sum.saturating_add(node.population)
+            }),
+        };
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.canonical.insert([nw, ne, sw, se], id);
+        id
+    }
+
+    pub fn empty_node(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let empty = *self.empty.last().unwrap();
+            let bigger = self.join(empty, empty, empty, empty);
+            self.empty.push(bigger);
+        }
+        self.empty[level as usize]
+    }
+
+    pub fn children(&self, id: NodeId) -> [NodeId; 4] {
+        let node = &self.nodes[id as usize];
+        [node.nw, node.ne, node.sw, node.se]
+    }
+
+    fn build(&mut self, level: u8, x: i128, y: i128, cells: &mut [(i128, i128)]) -> NodeId {
+        if cells.is_empty() {
+            return self.empty_node(level);
+        }
+        if level == 0 {
+            return ALIVE;
+        }
+        let half = 1i128 << (level - 1);
+        let quadrant =
+            |&(cx, cy): &(i128, i128)| (cy >= y + half) as u8 * 2 + (cx >= x + half) as u8;
+        cells.sort_unstable_by_key(quadrant);
+        let split =
+            |cells: &[(i128, i128)], q| cells.iter().take_while(|cell| quadrant(cell) < q).count();
+        let (ne_start, sw_start, se_start) = (split(cells, 1), split(cells, 2), split(cells, 3));
+        let (nw, rest) = cells.split_at_mut(ne_start);
+        let (ne, rest) = rest.split_at_mut(sw_start - ne_start);
+        let (sw, se) = rest.split_at_mut(se_start - sw_start);
+        let nw = self.build(level - 1, x, y, nw);
+        let ne = self.build(level - 1, x + half, y, ne);
+        let sw = self.build(level - 1, x, y + half, sw);
+        let se = self.build(level - 1, x + half, y + half, se);
+        self.join(nw, ne, sw, se)
+    }
+
+    // Surrounds root with empty space, keeping it centred on the same point
+    fn expand(&mut self) {
+        let level = self.level(self.root);
+        let empty = self.empty_node(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let nw = self.join(empty, empty, empty, nw);
+        let ne = self.join(empty, empty, ne, empty);
+        let sw = self.join(empty, sw, empty, empty);
+        let se = self.join(se, empty, empty, empty);
+        self.root = self.join(nw, ne, sw, se);
+        let shift = 1i128 << (level - 1);
+        self.origin_x -= shift;
+        self.origin_y -= shift;
+    }
+
+    // Replaces root by its centre for as long as that does not lose any live cells
+    fn shrink(&mut self) {
+        while self.level(self.root) > 3 {
+            let centre = self.centre(self.root);
+            if self.nodes[centre as usize].population != self.population() {
+                break;
+            }
+            let shift = 1i128 << (self.level(self.root) - 2);
+            self.root = centre;
+            self.origin_x += shift;
+            self.origin_y += shift;
+        }
+    }
+
+    // Copy of the node with the cell at (x, y), relative to its top left corner, set to alive
+    fn with_cell(&mut self, id: NodeId, x: i128, y: i128, alive: bool) -> NodeId {
+        if self.level(id) == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1i128 << (self.level(id) - 1);
+        let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+        let mut children = self.children(id);
+        children[quadrant] = self.with_cell(children[quadrant], x % half, y % half, alive);
+        let [nw, ne, sw, se] = children;
+        self.join(nw, ne, sw, se)
+    }
+
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        let (nw, ne, sw, se) = (
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        );
+        self.join(nw, ne, sw, se)
+    }
+
+    fn is_padded(&self, id: NodeId) -> bool {
+        let [nw, ne, sw, se] = self.children(id);
+        let inner = self.children(self.children(nw)[3])[3] as usize;
+        let inner_ne = self.children(self.children(ne)[2])[2] as usize;
+        let inner_sw = self.children(self.children(sw)[1])[1] as usize;
+        let inner_se = self.children(self.children(se)[0])[0] as usize;
+        self.nodes[inner].population
+            + self.nodes[inner_ne].population
+            + self.nodes[inner_sw].population
+            + self.nodes[inner_se].population
+            == self.nodes[id as usize].population
+    }
+
+    // Centre of a level k node advanced by 2^j generations, where j <= k - 2
+    fn successor(&mut self, id: NodeId, j: u8) -> NodeId {
+        let node = &self.nodes[id as usize];
+        if node.population == 0 {
+            return self.empty_node(node.level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, j)) {
+            return result;
+        }
+        let level = node.level;
+        let result = if level == 2 {
+            self.successor_4x4(id)
+        } else {
+            let [nw, ne, sw, se] = self.children(id);
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+
+            // The nine overlapping subsquares of half the size
+            let n00 = nw;
+            let n01 = self.join(nw_ne, ne_nw, nw_se, ne_sw);
+            let n02 = ne;
+            let n10 = self.join(nw_sw, nw_se, sw_nw, sw_ne);
+            let n11 = self.join(nw_se, ne_sw, sw_ne, se_nw);
+            let n12 = self.join(ne_sw, ne_se, se_nw, se_ne);
+            let n20 = sw;
+            let n21 = self.join(sw_ne, se_nw, sw_se, se_sw);
+            let n22 = se;
+
+            // Going full speed spends half of the generations here, otherwise they are all
+            // spent in the second half
+            let full_speed = j == level - 2;
+            let inner_j = j.min(level - 3);
+            let first_half = |hashlife: &mut Self, id| {
+                if full_speed {
+                    hashlife.successor(id, inner_j)
+                } else {
+                    hashlife.centre(id)
+                }
+            };
+            let r00 = first_half(self, n00);
+            let r01 = first_half(self, n01);
+            let r02 = first_half(self, n02);
+            let r10 = first_half(self, n10);
+            let r11 = first_half(self, n11);
+            let r12 = first_half(self, n12);
+            let r20 = first_half(self, n20);
+            let r21 = first_half(self, n21);
+            let r22 = first_half(self, n22);
+
+            let q00 = self.join(r00, r01, r10, r11);
+            let q01 = self.join(r01, r02, r11, r12);
+            let q10 = self.join(r10, r11, r20, r21);
+            let q11 = self.join(r11, r12, r21, r22);
+            let nw = self.successor(q00, inner_j);
+            let ne = self.successor(q01, inner_j);
+            let sw = self.successor(q10, inner_j);
+            let se = self.successor(q11, inner_j);
+            self.join(nw, ne, sw, se)
+        };
+        self.results.insert((id, j), result);
+        result
+    }
+
+    // Base case: the centre 2x2 cells of a 4x4 node after a single generation
+    fn successor_4x4(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (quadrant, child) in self.children(id).into_iter().enumerate() {
+            for (cell, leaf) in self.children(child).into_iter().enumerate() {
+                let x = (quadrant % 2) * 2 + cell % 2;
+                let y = (quadrant / 2) * 2 + cell / 2;
+                cells[y][x] = leaf == ALIVE;
+            }
+        }
+        let next = |x: usize, y: usize| {
+            let num_live_neighbors = (y - 1..=y + 1)
+                .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                .filter(|&(nx, ny)| (nx, ny) != (x, y) && cells[ny][nx])
+                .count();
+            if self.rule.next_alive(cells[y][x], num_live_neighbors) {
+                ALIVE
+            } else {
+                DEAD
+            }
+        };
+        let (nw, ne, sw, se) = (next(1, 1), next(2, 1), next(1, 2), next(2, 2));
+        self.join(nw, ne, sw, se)
+    }
+
+    // Gathers live cells of the node at (x, y) falling inside of the half open region
+    fn collect_cells(
+        &self,
+        id: NodeId,
+        x: i128,
+        y: i128,
+        region: (i128, i128, i128, i128),
+        cells: &mut Vec<Coord>,
+    ) {
+        let node = &self.nodes[id as usize];
+        let size = 1i128 << node.level;
+        let (min_x, min_y, max_x, max_y) = region;
+        if node.population == 0
+            || x >= max_x
+            || y >= max_y
+            || x + size <= min_x
+            || y + size <= min_y
+        {
+            return;
+        }
+        if node.level == 0 {
+            cells.push(Coord {
+                x: x as i32,
+                y: y as i32,
+            });
+            return;
+        }
+        let half = size / 2;
+        self.collect_cells(node.nw, x, y, region, cells);
+        self.collect_cells(node.ne, x + half, y, region, cells);
+        self.collect_cells(node.sw, x, y + half, region, cells);
+        self.collect_cells(node.se, x + half, y + half, region, cells);
+    }
+}
+
+impl Life for HashLife {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn is_alive(&self, coord: &Coord) -> bool {
+        let mut id = self.root;
+        let (mut x, mut y) = (
+            coord.x as i128 - self.origin_x,
+            coord.y as i128 - self.origin_y,
+        );
+        let size = 1i128 << self.level(id);
+        if !(0..size).contains(&x) || !(0..size).contains(&y) {
+            return false;
+        }
+        while self.level(id) > 0 {
+            if self.nodes[id as usize].population == 0 {
+                return false;
+            }
+            let half = 1i128 << (self.level(id) - 1);
+            let quadrant = (y >= half) as usize * 2 + (x >= half) as usize;
+            id = self.children(id)[quadrant];
+            x %= half;
+            y %= half;
+        }
+        id == ALIVE
+    }
+
+    fn set_alive(&mut self, coord: &Coord, alive: bool) {
+        let (x, y) = (coord.x as i128, coord.y as i128);
+        loop {
+            let size = 1i128 << self.level(self.root);
+            if (0..size).contains(&(x - self.origin_x)) && (0..size).contains(&(y - self.origin_y))
+            {
+                break;
+            }
+            self.expand();
+        }
+        self.root = self.with_cell(self.root, x - self.origin_x, y - self.origin_y, alive);
+    }
+
+    fn step(&mut self) {
+        self.advance(1);
+    }
+
+    fn live_cells(&self) -> Vec<Coord> {
+        let mut cells = Vec::new();
+        let all = (
+            i32::MIN as i128,
+            i32::MIN as i128,
+            i32::MAX as i128 + 1,
+            i32::MAX as i128 + 1,
+        );
+        self.collect_cells(self.root, self.origin_x, self.origin_y, all, &mut cells);
+        cells
+    }
+
+    fn quadtree(&self) -> Option<&HashLife> {
+        Some(self)
+    }
+
+    fn viewport(&self) -> (Coord, u32, u32) {
+        (self.view_origin.clone(), self.view_width, self.view_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sparse::SparseLife;
+
+    fn r_pentomino() -> Vec<Coord> {
+        [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]
+            .map(|(x, y)| Coord {
+                x: x + 20,
+                y: y + 20,
+            })
+            .to_vec()
+    }
+
+    fn sorted(mut cells: Vec<Coord>) -> Vec<Coord> {
+        cells.sort_by_key(|coord| (coord.y, coord.x));
+        cells
+    }
+
+    fn stepped(rule: Rule, cells: Vec<Coord>, generations: u64) -> Vec<Coord> {
+        let mut sparse = SparseLife::from_iter(rule, cells.into_iter()).unwrap();
+        for _ in 0..generations {
+            sparse.step();
+        }
+        sorted(sparse.live_cells())
+    }
+
+    #[test]
+    fn advance_matches_single_steps() {
+        for rule in ["B3/S23", "B36/S23"] {
+            let rule: Rule = rule.parse().unwrap();
+            for generations in [1, 2, 3, 5, 7, 12, 37, 100, 129] {
+                let mut hashlife = HashLife::from_iter(rule, r_pentomino().into_iter()).unwrap();
+                hashlife.advance(generations);
+                assert_eq!(
+                    sorted(hashlife.live_cells()),
+                    stepped(rule, r_pentomino(), generations),
+                    "rule {} after {} generations",
+                    rule,
+                    generations
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn advance_adds_up() {
+        let mut hashlife = HashLife::from_iter(Rule::default(), r_pentomino().into_iter()).unwrap();
+        for generations in [3, 1, 6, 17, 50] {
+            hashlife.advance(generations);
+        }
+        assert_eq!(
+            sorted(hashlife.live_cells()),
+            stepped(Rule::default(), r_pentomino(), 77)
+        );
+    }
+
+    #[test]
+    fn garbage_collection_keeps_the_pattern() {
+        let mut unlimited =
+            HashLife::from_iter(Rule::default(), r_pentomino().into_iter()).unwrap();
+        let mut limited = HashLife::from_iter(Rule::default(), r_pentomino().into_iter())
+            .unwrap()
+            .with_memory_limit(0);
+        for _ in 0..60 {
+            unlimited.advance(9);
+            limited.advance(9);
+        }
+        assert!(limited.nodes.len() < unlimited.nodes.len());
+        assert_eq!(limited.population(), unlimited.population());
+        assert_eq!(sorted(limited.live_cells()), sorted(unlimited.live_cells()));
+        assert_eq!(
+            sorted(limited.live_cells()),
+            stepped(Rule::default(), r_pentomino(), 540)
+        );
+    }
+
+    #[test]
+    fn converts_to_and_from_gol() {
+        let gol = GOL::from_iter(40, 30, r_pentomino().into_iter());
+        let hashlife = HashLife::from_gol(&gol);
+        assert_eq!(hashlife.population(), 5);
+        assert_eq!(
+            sorted(hashlife.to_gol(40, 30).live_cells()),
+            sorted(gol.live_cells())
+        );
+        // Cells outside of the board are left out
+        assert_eq!(hashlife.to_gol(22, 22).live_cells().len(), 3);
+    }
+
+    #[test]
+    fn rejects_b0_rules() {
+        let rule: Rule = "B03/S23".parse().unwrap();
+        assert_eq!(
+            HashLife::from_iter(rule, r_pentomino().into_iter()).err(),
+            Some(HashLifeError::BirthOnZero(rule))
+        );
+        let mut hashlife = HashLife::new(Rule::default()).unwrap();
+        assert_eq!(
+            hashlife.set_rule(rule),
+            Err(HashLifeError::BirthOnZero(rule))
+        );
+        assert_eq!(hashlife.rule(), &Rule::default());
+    }
+}
@@ -1,92 +1,88 @@
-// Stolen from https://github.com/tsoding/carrotson/blob/master/carrotson.rs
-struct LCG {
-    state: u64,
-}
-
-impl LCG {
-    fn from_sys_timestamp() -> Self {
-        Self::new(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as u64,
-        )
-    }
-
-    fn new(seed: u64) -> Self {
-        Self { state: seed }
-    }
+#![allow(clippy::upper_case_acronyms)]
 
-    fn random_u32(&mut self) -> u32 {
-        // Stolen from https://en.wikipedia.org/wiki/Linear_congruential_generator
-        // Using the values of MMIX by Donald Knuth
-        const RAND_A: u64 = 6364136223846793005;
-        const RAND_C: u64 = 1442695040888963407;
-        (self.state, _) = self.state.overflowing_mul(RAND_A);
-        (self.state, _) = self.state.overflowing_add(RAND_C);
-        return (self.state >> 32) as u32;
-    }
-}
+mod apgcode;
+mod bitgrid;
+mod census;
+mod cli;
+mod detect;
+mod hashlife;
+mod palette;
+mod pattern;
+mod render;
+mod rng;
+mod rule;
+mod soup;
+mod sparse;
+mod stats;
+mod terminal;
+mod ui;
 
-impl Iterator for LCG {
-    type Item = u32;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        Some(self.random_u32())
-    }
-}
+use bitgrid::BitGrid;
+use census::Census;
+use cli::{Args, ArgsError, Engine};
+use hashlife::HashLife;
+use pattern::{macrocell, Format, Pattern};
+use rng::Seed;
+use rule::Rule;
+use soup::{Soup, Symmetry};
+use sparse::SparseLife;
+use stats::{StatsFormat, StatsWriter};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 struct Coord {
-    x: i16,
-    y: i16,
+    x: i32,
+    y: i32,
 }
 
 impl Coord {
-    fn step(&self, velx: i16, vely: i16) -> Coord {
+    // Saturates at the edges of the coordinate space instead of overflowing
+    fn step(&self, velx: i32, vely: i32) -> Coord {
         Self {
-            x: self.x + velx,
-            y: self.y + vely,
+            x: self.x.saturating_add(velx),
+            y: self.y.saturating_add(vely),
         }
     }
 
+    // None when the result falls off the edges of the coordinate space
+    fn checked_step(&self, velx: i32, vely: i32) -> Option<Coord> {
+        Some(Self {
+            x: self.x.checked_add(velx)?,
+            y: self.y.checked_add(vely)?,
+        })
+    }
+
+    // Neighbors off the edges of the coordinate space are left out, so they count as dead
     fn neighbors(&self) -> Vec<Coord> {
-        vec![
-            self.step(-1, -1),
-            self.step(0, -1),
-            self.step(1, -1),
-            self.step(-1, 0),
-            self.step(1, 0),
-            self.step(-1, 1),
-            self.step(0, 1),
-            self.step(1, 1),
+        [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
         ]
+        .into_iter()
+        .filter_map(|(velx, vely)| self.checked_step(velx, vely))
+        .collect()
     }
 
-    fn wrap(&self, width: u8, height: u8) -> Self {
+    fn wrap(&self, width: u32, height: u32) -> Self {
         Self {
-            x: self.x.rem_euclid(width as i16),
-            y: self.y.rem_euclid(height as i16),
+            x: (self.x as i64).rem_euclid(width as i64) as i32,
+            y: (self.y as i64).rem_euclid(height as i64) as i32,
         }
     }
 
-    fn index_in(&self, width: u8, height: u8) -> usize {
-        let coord = self.wrap(width, height);
-        (coord.y as usize) * (width as usize) + (coord.x as usize)
+    fn is_within(&self, width: u32, height: u32) -> bool {
+        (0..width as i64).contains(&(self.x as i64))
+            && (0..height as i64).contains(&(self.y as i64))
     }
 
-    fn random_coords(rng: &mut LCG, take: usize) -> Vec<Self> {
-        (0..usize::MAX)
-            .map(|_| {
-                let x = rng.next().unwrap();
-                let y = rng.next().unwrap();
-                Self {
-                    x: x as i16,
-                    y: y as i16,
-                }
-            })
-            .take(take)
-            .collect()
+    fn index_in(&self, width: u32, height: u32) -> usize {
+        let coord = self.wrap(width, height);
+        (coord.y as usize) * (width as usize) + (coord.x as usize)
     }
 }
 
@@ -96,82 +92,207 @@ enum CellStatus {
     Dead,
 }
 
+// Generations since a cell was born, or since it died
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Age {
+    Alive(u32),
+    Dead(u32),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Topology {
+    // Edges wrap around, so the board is the surface of a donut
+    #[default]
+    Torus,
+    // Cells outside of the board are permanently dead
+    Bounded,
+}
+
+impl Topology {
+    fn index(&self, coord: &Coord, width: u32, height: u32) -> Option<usize> {
+        match self {
+            Topology::Torus => Some(coord.index_in(width, height)),
+            Topology::Bounded if coord.is_within(width, height) => {
+                Some(coord.index_in(width, height))
+            }
+            Topology::Bounded => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct GOL {
-    width: u8,
-    height: u8,
+    width: u32,
+    height: u32,
     grid: Vec<CellStatus>,
+    rule: Rule,
+    topology: Topology,
+    // Number of horizontal bands stepped in parallel
+    threads: usize,
+    // Generations since each cell last changed state, only tracked when asked for.
+    // Cells that never lived count from u32::MAX so they never look recently dead
+    ages: Option<Vec<u32>>,
 }
 
-impl GOL {
+// Common interface of the simulation engines, so every one of them can be stepped and drawn
+trait Life {
+    fn rule(&self) -> &Rule;
+
+    fn is_alive(&self, coord: &Coord) -> bool;
+
+    // Cells off a bounded board are left alone
+    fn set_alive(&mut self, coord: &Coord, alive: bool);
+
+    fn step(&mut self);
+
+    fn live_cells(&self) -> Vec<Coord>;
+
+    // Width and height of the board when its edges wrap around
+    fn torus_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    // Width and height of a board of fixed size, None on the infinite plane
+    fn board_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    // Only engines that track cell ages know them
+    fn age(&self, _coord: &Coord) -> Option<Age> {
+        None
+    }
+
+    // Engines that are a HashLife quadtree can be saved as macrocells without expanding them
+    fn quadtree(&self) -> Option<&HashLife> {
+        None
+    }
+
+    // Top left corner, width and height of the part of the board that gets drawn
+    fn viewport(&self) -> (Coord, u32, u32);
+
+    // Canonical apgcode of the whole board, None unless it is a single still life, oscillator or
+    // spaceship. A torus is unwrapped first, so objects across its edges stay in one piece
+    fn apgcode(&self) -> Option<String> {
+        let (cells, _) = detect::normalise(&self.live_cells(), self.torus_size());
+        if census::split(&cells.iter().cloned().collect()).len() != 1 {
+            return None;
+        }
+        apgcode::identify(*self.rule(), cells, apgcode::MAX_PERIOD).map(|(code, _)| code)
+    }
+}
+
+impl Life for GOL {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
     fn is_alive(&self, coord: &Coord) -> bool {
-        self.grid[coord.index_in(self.width, self.height)] == CellStatus::Alive
+        self.topology
+            .index(coord, self.width, self.height)
+            .is_some_and(|idx| self.grid[idx] == CellStatus::Alive)
+    }
+
+    fn set_alive(&mut self, coord: &Coord, alive: bool) {
+        if let Some(idx) = self.topology.index(coord, self.width, self.height) {
+            self.grid[idx] = if alive {
+                CellStatus::Alive
+            } else {
+                CellStatus::Dead
+            };
+            if let Some(ages) = &mut self.ages {
+                ages[idx] = 0;
+            }
+        }
     }
 
     fn step(&mut self) {
+        let width = self.width as usize;
+        if width == 0 {
+            return;
+        }
         let mut next_grid = self.grid.clone();
-        for y in 0..self.height as i16 {
-            for x in 0..self.width as i16 {
-                let coord = Coord { x, y };
-                let idx = coord.index_in(self.width, self.height);
-                let num_live_neighbors = coord
-                    .neighbors()
-                    .into_iter()
-                    .filter(|coord| self.is_alive(&coord))
-                    .count();
-                next_grid[idx] = match (&self.grid[idx], num_live_neighbors) {
-                    // Rule 1. Any live cell with fewer than 2 live neighbors dies, as if casued by underpopulation
-                    (CellStatus::Alive, x) if x < 2 => CellStatus::Dead,
-                    // Rule 2. Any live cell with 2 or 3 live neighbors get's to survive to the next generation
-                    (CellStatus::Alive, 2) | (CellStatus::Alive, 3) => CellStatus::Alive,
-                    // Rule 3. Any live cell with more than 3 live neighbors dies, as if caused by overpopulation
-                    (CellStatus::Alive, x) if x > 3 => CellStatus::Dead,
-                    // Rule 4. Any dead cell with exactly three neighbors becomes alive, as if by reproduction
-                    (CellStatus::Dead, 3) => CellStatus::Alive,
-                    // All other cells remain in the same state
-                    (otherwise, _) => otherwise.clone(),
+        // Bands only read the current grid, so rows next to a band boundary (or wrapping around
+        // the torus) see exactly the same neighbors as they would sequentially
+        let band_rows = (self.height as usize).div_ceil(self.threads).max(1);
+        let bands = next_grid.chunks_mut(band_rows * width).enumerate();
+        if self.threads <= 1 {
+            bands.for_each(|(band, cells)| self.step_band(band * band_rows, cells));
+        } else {
+            let gol = &*self;
+            std::thread::scope(|scope| {
+                for (band, cells) in bands {
+                    scope.spawn(move || gol.step_band(band * band_rows, cells));
+                }
+            });
+        }
+        if let Some(ages) = &mut self.ages {
+            for ((age, previous), next) in ages.iter_mut().zip(&self.grid).zip(&next_grid) {
+                *age = if previous == next {
+                    age.saturating_add(1)
+                } else {
+                    0
                 };
             }
         }
         self.grid = next_grid;
     }
 
-    fn print_to_console(&self) {
-        print!(" ");
-        println!("{}", "# ".repeat(self.width as usize + 1));
-        for y in 0..self.height as i16 {
-            print!("# ");
-            for x in 0..self.width as i16 {
-                let coord = Coord { x, y };
-                let idx = coord.index_in(self.width, self.height);
-                if self.grid[idx] == CellStatus::Alive {
-                    print!("o ");
-                } else {
-                    print!("  ");
-                }
-            }
-            println!("#");
-        }
-        print!(" ");
-        println!("{}", "# ".repeat(self.width as usize + 1));
+    fn live_cells(&self) -> Vec<Coord> {
+        (0..self.height as i32)
+            .flat_map(|y| (0..self.width as i32).map(move |x| Coord { x, y }))
+            .filter(|coord| self.is_alive(coord))
+            .collect()
+    }
+
+    fn torus_size(&self) -> Option<(u32, u32)> {
+        (self.topology == Topology::Torus).then_some((self.width, self.height))
+    }
+
+    fn board_size(&self) -> Option<(u32, u32)> {
+        Some((self.width, self.height))
     }
 
-    fn clear_console() {
-        print!("\x1B[2J\x1B[1;1H");
+    fn age(&self, coord: &Coord) -> Option<Age> {
+        let ages = self.ages.as_ref()?;
+        let idx = self.topology.index(coord, self.width, self.height)?;
+        Some(match self.grid[idx] {
+            CellStatus::Alive => Age::Alive(ages[idx]),
+            CellStatus::Dead => Age::Dead(ages[idx]),
+        })
     }
 
-    fn simulate(&mut self, speed: std::time::Duration) {
-        print!("\x1b[?25l");
-        loop {
-            self.print_to_console();
-            self.step();
-            std::thread::sleep(speed);
-            GOL::clear_console();
+    fn viewport(&self) -> (Coord, u32, u32) {
+        (Coord { x: 0, y: 0 }, self.width, self.height)
+    }
+}
+
+impl GOL {
+    // Computes the next generation of the rows starting at first_row into cells
+    fn step_band(&self, first_row: usize, cells: &mut [CellStatus]) {
+        let width = self.width as usize;
+        for (offset, next) in cells.iter_mut().enumerate() {
+            let coord = Coord {
+                x: (offset % width) as i32,
+                y: (first_row + offset / width) as i32,
+            };
+            let num_live_neighbors = coord
+                .neighbors()
+                .into_iter()
+                .filter(|coord| self.is_alive(coord))
+                .count();
+            *next = if self
+                .rule
+                .next_alive(self.is_alive(&coord), num_live_neighbors)
+            {
+                CellStatus::Alive
+            } else {
+                CellStatus::Dead
+            };
         }
     }
 
     #[allow(dead_code)]
-    fn glider_pattern(width: u8, height: u8) -> Self {
+    fn glider_pattern(width: u32, height: u32) -> Self {
         GOL::from_iter(
             width,
             height,
@@ -186,29 +307,501 @@ impl GOL {
         )
     }
 
-    fn from_iter(width: u8, height: u8, live_coords: impl Iterator<Item = Coord>) -> Self {
+    #[allow(dead_code)]
+    fn from_iter(width: u32, height: u32, live_coords: impl Iterator<Item = Coord>) -> Self {
+        GOL::from_iter_with_topology(width, height, Topology::default(), live_coords)
+    }
+
+    fn from_iter_with_topology(
+        width: u32,
+        height: u32,
+        topology: Topology,
+        live_coords: impl Iterator<Item = Coord>,
+    ) -> Self {
         let mut grid: Vec<CellStatus> = (0..(width as usize * height as usize))
-            .into_iter()
             .map(|_| CellStatus::Dead)
             .collect();
 
-        live_coords.for_each(|coord| {
-            let idx = coord.index_in(width, height);
-            grid[idx] = CellStatus::Alive;
-        });
+        live_coords
+            .filter_map(|coord| topology.index(&coord, width, height))
+            .for_each(|idx| grid[idx] = CellStatus::Alive);
 
         GOL {
             width,
             height,
             grid,
+            rule: Rule::default(),
+            topology,
+            threads: 1,
+            ages: None,
         }
     }
+
+    fn with_rule(mut self, rule: Rule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    // Starts tracking how long cells have been alive or dead, as if the board was just placed
+    fn with_ages(mut self) -> Self {
+        let ages = self
+            .grid
+            .iter()
+            .map(|cell| match cell {
+                CellStatus::Alive => 0,
+                CellStatus::Dead => u32::MAX,
+            })
+            .collect();
+        self.ages = Some(ages);
+        self
+    }
+}
+
+const DEFAULT_SIZE: u32 = 15;
+// Empty cells left around a pattern when the board is sized to fit it
+const PATTERN_MARGIN: u32 = 10;
+// Soups of a census are 16 by 16 cells unless told otherwise, like in apgsearch
+const CENSUS_SOUP_SIZE: u32 = 16;
+// Runs without animation that wait for the board to settle give up after that many generations
+// unless told otherwise, as boards that never settle, or repeat with a longer period than the
+// cycle detector remembers, would keep them going forever
+const MAX_GENERATIONS_UNTIL_STABLE: u64 = 10_000;
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
 }
 
 fn main() {
-    let mut rng = LCG::from_sys_timestamp();
-    let mut gol = GOL::from_iter(15, 15, Coord::random_coords(&mut rng, 100).into_iter());
-    // let mut gol = GOL::glider_pattern(15, 15);
-    // println!("{:?}", gol);
-    gol.simulate(std::time::Duration::from_millis(100));
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(ArgsError::HelpRequested) => {
+            print!("{}", cli::USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            eprintln!("Run with --help to see the available options");
+            std::process::exit(2);
+        }
+    };
+
+    if args.pattern.is_some() && args.apgcode.is_some() {
+        fail("--pattern and --apgcode both pick the starting pattern, use only one of them");
+    }
+    let loaded = args.pattern.as_ref().map(|path| {
+        let text = std::fs::read_to_string(path)
+            .unwrap_or_else(|err| fail(format!("could not read {}: {}", path.display(), err)));
+        (path, Format::detect(&text), text)
+    });
+    let engine = args.engine.unwrap_or(match &loaded {
+        Some((_, Format::Macrocell, _)) => Engine::HashLife,
+        _ => Engine::Dense,
+    });
+    let located = |path: &std::path::Path, err| format!("{}:{}", path.display(), err);
+    // Macrocell patterns stay a quadtree when they are going to run on the hashlife engine
+    let (pattern, quadtree) = match loaded {
+        Some((path, Format::Macrocell, text)) if engine == Engine::HashLife => {
+            let (pattern, hashlife) =
+                macrocell::parse(&text).unwrap_or_else(|err| fail(located(path, err)));
+            (Some(pattern), Some(hashlife))
+        }
+        Some((path, format, text)) => {
+            let pattern = format
+                .parse(&text)
+                .unwrap_or_else(|err| fail(located(path, err)));
+            (Some(pattern), None)
+        }
+        None => match &args.apgcode {
+            Some(code) => {
+                let cells = apgcode::decode(code).unwrap_or_else(|err| fail(err.to_string()));
+                let pattern = Pattern {
+                    name: Some(code.trim().to_string()),
+                    ..Pattern::from_cells(None, cells)
+                };
+                (Some(pattern), None)
+            }
+            None => (None, None),
+        },
+    };
+
+    // Patterns and soups smaller than the board leave some room to grow around them
+    let fit = |pattern_size: u32| {
+        pattern_size
+            .saturating_add(2 * PATTERN_MARGIN)
+            .max(DEFAULT_SIZE)
+    };
+    let region = match &pattern {
+        Some(pattern) => Some((pattern.width, pattern.height)),
+        None => args.soup_size,
+    };
+    let width = args
+        .width
+        .unwrap_or(region.map_or(DEFAULT_SIZE, |(width, _)| fit(width)));
+    let height = args
+        .height
+        .unwrap_or(region.map_or(DEFAULT_SIZE, |(_, height)| fit(height)));
+    let rule = args
+        .rule
+        .or(pattern.as_ref().and_then(|pattern| pattern.rule))
+        .unwrap_or_default();
+
+    let seed = args.seed.clone().unwrap_or_else(Seed::from_time);
+    if let Some(soups) = args.census {
+        let (soup_width, soup_height) = args
+            .soup_size
+            .unwrap_or((CENSUS_SOUP_SIZE, CENSUS_SOUP_SIZE));
+        let soup = Soup::new(soup_width, soup_height, args.density).with_symmetry(args.symmetry);
+        let mut census = Census::new(
+            rule,
+            format!(
+                "--soup-size {}x{} --density {} --symmetry {} --rng {}",
+                soup_width, soup_height, args.density, args.symmetry, args.generator
+            ),
+        )
+        .unwrap_or_else(|err| fail(err));
+        let started = std::time::Instant::now();
+        // Every soup has a seed of its own, so any of them can be replayed with --seed and the
+        // options written at the top of the report
+        for i in 0..soups {
+            let seed = Seed::Text(format!("{}_{}", seed, i));
+            let mut rng = args.generator.seeded(seed.value());
+            census.add_soup(&seed, soup.generate(rng.as_mut()).into_iter());
+        }
+        let written = match &args.census_report {
+            Some(path) => std::fs::File::create(path)
+                .and_then(|file| census.write(&mut std::io::BufWriter::new(file))),
+            None => census.write(&mut std::io::stdout()),
+        };
+        written.unwrap_or_else(|err| fail(format!("could not write the census report: {}", err)));
+        eprintln!("{} soups in {:?}", soups, started.elapsed());
+        return;
+    }
+    let (soup_width, soup_height) = args.soup_size.unwrap_or((width, height));
+    let soup = Soup::new(soup_width, soup_height, args.density).with_symmetry(args.symmetry);
+    let region = match &pattern {
+        Some(pattern) => (pattern.width, pattern.height),
+        None => soup.size(),
+    };
+    let offset = args.offset.clone().unwrap_or(Coord {
+        x: (width as i64 - region.0 as i64).clamp(0, i32::MAX as i64) as i32 / 2,
+        y: (height as i64 - region.1 as i64).clamp(0, i32::MAX as i64) as i32 / 2,
+    });
+    let soup = |seed: &Seed| -> Vec<Coord> {
+        let mut rng = args.generator.seeded(seed.value());
+        soup.generate(rng.as_mut())
+            .into_iter()
+            .filter_map(|coord| coord.checked_step(offset.x, offset.y))
+            .collect()
+    };
+    let soup_caption = |seed: &Seed| match args.symmetry {
+        Symmetry::C1 => format!("rule {} | seed {}", rule, seed),
+        symmetry => format!("rule {} | seed {} | {}", rule, seed, symmetry),
+    };
+    let (live_coords, caption): (Vec<Coord>, String) = match &pattern {
+        Some(pattern) => {
+            // Patterns without a name were loaded from a file
+            let name = pattern
+                .name
+                .clone()
+                .unwrap_or_else(|| args.pattern.as_ref().unwrap().display().to_string());
+            (
+                pattern.cells_at(&offset).collect(),
+                format!("rule {} | {}", rule, name),
+            )
+        }
+        None => (soup(&seed), soup_caption(&seed)),
+    };
+
+    let build = |live_coords: Vec<Coord>, quadtree: Option<HashLife>| -> Box<dyn Life> {
+        let dense = || {
+            let gol = GOL::from_iter_with_topology(
+                width,
+                height,
+                args.topology,
+                live_coords.iter().cloned(),
+            )
+            .with_rule(rule)
+            .with_threads(args.threads);
+            if args.colors.is_enabled() {
+                gol.with_ages()
+            } else {
+                gol
+            }
+        };
+        let origin = Coord { x: 0, y: 0 };
+        match engine {
+            Engine::Dense => Box::new(dense()),
+            Engine::BitGrid => Box::new(BitGrid::from_gol(&dense())),
+            Engine::Sparse => Box::new(
+                SparseLife::from_iter(rule, live_coords.iter().cloned())
+                    .unwrap_or_else(|err| fail(err))
+                    .with_viewport(origin, width, height),
+            ),
+            Engine::HashLife => {
+                let hashlife = match quadtree {
+                    Some(mut quadtree) => {
+                        quadtree.set_rule(rule).unwrap_or_else(|err| fail(err));
+                        quadtree.translate(offset.x as i128, offset.y as i128);
+                        quadtree
+                    }
+                    None => HashLife::from_iter(rule, live_coords.iter().cloned())
+                        .unwrap_or_else(|err| fail(err)),
+                };
+                let hashlife = hashlife.with_viewport(origin, width, height);
+                match args.memory_limit {
+                    Some(bytes) => Box::new(hashlife.with_memory_limit(bytes)),
+                    None => Box::new(hashlife),
+                }
+            }
+        }
+    };
+    // Soups picked with r follow from the initial seed, so a whole session can be replayed
+    let mut seeds = args.generator.seeded(seed.value());
+    let reseed = || {
+        let seed = Seed::Number(seeds.next_u64());
+        (build(soup(&seed), None), soup_caption(&seed))
+    };
+    // Statistics written to stdout replace the animation, which would get mixed up with them
+    let stats_to_stdout = args.stats.as_deref() == Some(std::path::Path::new("-"));
+    let headless = args.batch || stats_to_stdout;
+    // The summary of --batch goes to stdout as well
+    if args.batch && stats_to_stdout {
+        fail("--batch can not write statistics to stdout, give --stats a file");
+    }
+    if headless && args.generations.is_none() && !args.until_stable {
+        fail(if args.batch {
+            "--batch needs --generations or --until-stable to know when to stop"
+        } else {
+            "--stats - needs --generations or --until-stable to know when to stop"
+        });
+    }
+    let generations = match args.generations {
+        None if headless && args.until_stable => Some(MAX_GENERATIONS_UNTIL_STABLE),
+        generations => generations,
+    };
+    let settings = ui::Settings {
+        delay: args.delay,
+        generations,
+        renderer: args.renderer,
+        colors: args.colors,
+        until_stable: args.until_stable,
+    };
+    let stats = args.stats.as_ref().map(|path| {
+        let format = args
+            .stats_format
+            .or_else(|| StatsFormat::from_path(path))
+            .unwrap_or(StatsFormat::Csv);
+        let out: Box<dyn std::io::Write> = if stats_to_stdout {
+            Box::new(std::io::stdout())
+        } else {
+            let file = std::fs::File::create(path).unwrap_or_else(|err| {
+                fail(format!("could not create {}: {}", path.display(), err))
+            });
+            Box::new(std::io::BufWriter::new(file))
+        };
+        StatsWriter::new(out, format)
+            .unwrap_or_else(|err| fail(format!("could not write statistics: {}", err)))
+    });
+    let life = build(live_coords, quadtree);
+    let started = std::time::Instant::now();
+    let run = if headless {
+        ui::run_headless(life, settings, stats)
+    } else {
+        ui::simulate(life, caption, settings, stats, reseed)
+    }
+    .unwrap_or_else(|err| fail(format!("could not write statistics: {}", err)));
+    let elapsed = started.elapsed();
+    let life = run.life;
+    let population = life.live_cells().len();
+    if args.until_stable {
+        let summary = match run.cycle {
+            Some(cycle) if population == 0 => format!("died out at generation {}", cycle.start),
+            Some(cycle) => {
+                let mut summary = format!(
+                    "stable after {} generations: {}, population {}",
+                    run.generation, cycle, population
+                );
+                if let Some(code) = life.apgcode() {
+                    summary.push_str(&format!(", apgcode {}", code));
+                }
+                summary
+            }
+            None => format!(
+                "not stable after {} generations, population {}",
+                run.generation, population
+            ),
+        };
+        // Keeps stdout clean for the statistics
+        if stats_to_stdout {
+            eprintln!("{}", summary);
+        } else {
+            println!("{}", summary);
+        }
+    }
+
+    let save = |format: Format| {
+        // The name and comments of a loaded pattern are written back out
+        let (name, comments) = match &pattern {
+            Some(pattern) => (pattern.name.clone(), pattern.comments.clone()),
+            None => (None, Vec::new()),
+        };
+        match (format, life.quadtree()) {
+            (Format::Macrocell, Some(quadtree)) => {
+                let metadata = Pattern {
+                    name,
+                    comments,
+                    rule: Some(*life.rule()),
+                    ..Pattern::default()
+                };
+                macrocell::write(&metadata, quadtree)
+            }
+            _ => format.write(&Pattern {
+                name,
+                comments,
+                ..Pattern::from_cells(Some(*life.rule()), life.live_cells())
+            }),
+        }
+    };
+    if args.batch {
+        print!("{}", save(args.output_format.unwrap_or(Format::Plaintext)));
+        // A soup can be replayed from its seed
+        if pattern.is_none() {
+            println!("seed {}", seed);
+        }
+        println!("generations {}", run.generation);
+        println!("population {}", population);
+        println!("elapsed {:?}", elapsed);
+    }
+    if let Some(path) = &args.output {
+        let format = args
+            .output_format
+            .or_else(|| Format::from_path(path))
+            .unwrap_or(Format::Rle);
+        std::fs::write(path, save(format))
+            .unwrap_or_else(|err| fail(format!("could not write {}: {}", path.display(), err)));
+    }
+    if let Some(expected) = args.expect_population {
+        if population != expected {
+            fail(format!(
+                "expected a population of {} after {} generations, found {}",
+                expected, run.generation, population
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Xoshiro256;
+
+    #[test]
+    fn threads_step_like_a_single_thread() {
+        for topology in [Topology::Torus, Topology::Bounded] {
+            // Bands of 1 row, bands that do not divide the height, and more threads than rows
+            for (width, height) in [(20, 1), (9, 2), (13, 7), (1, 9), (31, 16)] {
+                for threads in [2, 3, 4, 8, 40] {
+                    let gol = || {
+                        let cells = Soup::new(width, height, 0.4)
+                            .generate(&mut Xoshiro256::new(height as u64));
+                        GOL::from_iter_with_topology(width, height, topology, cells.into_iter())
+                    };
+                    let mut sequential = gol();
+                    let mut threaded = gol().with_threads(threads);
+                    for generation in 1..=20 {
+                        sequential.step();
+                        threaded.step();
+                        assert!(
+                            threaded.grid == sequential.grid,
+                            "{:?} {}x{} with {} threads at generation {}",
+                            topology,
+                            width,
+                            height,
+                            threads,
+                            generation
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbors_stop_at_the_edges_of_the_plane() {
+        let corner = Coord {
+            x: i32::MAX,
+            y: i32::MIN,
+        };
+        assert_eq!(corner.neighbors().len(), 3);
+        assert!(!corner.neighbors().contains(&corner));
+        assert_eq!(Coord { x: i32::MIN, y: 0 }.neighbors().len(), 5);
+        assert_eq!(Coord { x: 0, y: 0 }.neighbors().len(), 8);
+    }
+
+    #[test]
+    fn glider_crosses_band_boundaries_on_the_torus() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| Coord { x, y });
+        let mut sequential = GOL::from_iter(12, 10, glider.clone().into_iter());
+        let mut threaded = GOL::from_iter(12, 10, glider.into_iter()).with_threads(3);
+        // 40 generations take it once around the board vertically, wrapping from the last band
+        // to the first
+        for _ in 0..40 {
+            sequential.step();
+            threaded.step();
+            assert!(threaded.grid == sequential.grid);
+        }
+        assert_eq!(threaded.live_cells().len(), 5);
+    }
+
+    #[test]
+    fn names_boards_of_a_single_object() {
+        let cells = |cells: &[(i32, i32)]| cells.iter().map(|&(x, y)| Coord { x, y }).collect();
+        let board = |cells: Vec<Coord>| GOL::from_iter(15, 15, cells.into_iter());
+        // A block across the corner of the torus is still a block
+        let block = board(cells(&[(14, 14), (0, 14), (14, 0), (0, 0)]));
+        assert_eq!(block.apgcode().as_deref(), Some("xs4_33"));
+        let blinker = board(cells(&[(6, 7), (7, 7), (8, 7)]));
+        assert_eq!(blinker.apgcode().as_deref(), Some("xp2_7"));
+        let blinkers = board(cells(&[(1, 1), (2, 1), (3, 1), (9, 9), (10, 9), (11, 9)]));
+        assert_eq!(blinkers.apgcode(), None);
+        assert_eq!(board(Vec::new()).apgcode(), None);
+    }
+
+    #[test]
+    fn bounded_board_stops_a_glider_at_its_edge() {
+        let cells = |cells: &[(i32, i32)]| -> Vec<Coord> {
+            cells.iter().map(|&(x, y)| Coord { x, y }).collect()
+        };
+        let glider = cells(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut bounded =
+            GOL::from_iter_with_topology(8, 8, Topology::Bounded, glider.clone().into_iter());
+        let mut torus = GOL::from_iter(8, 8, glider.into_iter());
+        for _ in 0..30 {
+            bounded.step();
+            torus.step();
+        }
+        // It crashes into the corner and leaves a block, while on the torus it flies on
+        assert_eq!(
+            bounded.live_cells(),
+            cells(&[(6, 6), (7, 6), (6, 7), (7, 7)])
+        );
+        assert_eq!(torus.live_cells().len(), 5);
+    }
+
+    #[test]
+    fn bounded_board_drops_cells_off_it() {
+        let outside = [(-1, 0), (8, 3), (3, 8), (2, 2)].map(|(x, y)| Coord { x, y });
+        let bounded =
+            GOL::from_iter_with_topology(8, 8, Topology::Bounded, outside.clone().into_iter());
+        assert_eq!(bounded.live_cells(), [Coord { x: 2, y: 2 }]);
+        assert!(!bounded.is_alive(&Coord { x: 8, y: 3 }));
+        let torus = GOL::from_iter(8, 8, outside.into_iter());
+        assert_eq!(torus.live_cells().len(), 4);
+    }
 }
@@ -0,0 +1,147 @@
+// Colours of cells by age: new births fade into the colour of stable cells, and dead cells leave
+// a trail that fades out over a few generations.
+use std::str::FromStr;
+
+use crate::Age;
+
+// Generations until a newborn cell reaches the colour of old cells
+const AGE_GRADIENT: u32 = 32;
+// Generations a dead cell stays visible
+const TRAIL_LENGTH: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // Blends towards other, t = 0 is self and t = 1 is other
+    fn mix(self, other: Rgb, t: f64) -> Rgb {
+        let channel =
+            |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+        Rgb(
+            channel(self.0, other.0),
+            channel(self.1, other.1),
+            channel(self.2, other.2),
+        )
+    }
+
+    // Closest colour of the 6x6x6 cube of the 256 colour palette
+    fn ansi_256(self) -> u8 {
+        let level = |channel: u8| (channel as u16 * 5 + 127) / 255;
+        (16 + 36 * level(self.0) + 6 * level(self.1) + level(self.2)) as u8
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    // Hex colours such as ff8000 or #ff8000
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return Err(format!("`{}` is not a hex colour such as ff8000", s.trim()));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    born: Rgb,
+    old: Rgb,
+    trail: Rgb,
+}
+
+impl Palette {
+    pub const HEAT: Palette = Palette {
+        born: Rgb(255, 255, 160),
+        old: Rgb(200, 30, 30),
+        trail: Rgb(90, 60, 160),
+    };
+    pub const OCEAN: Palette = Palette {
+        born: Rgb(200, 255, 255),
+        old: Rgb(0, 90, 200),
+        trail: Rgb(0, 90, 110),
+    };
+    pub const FOREST: Palette = Palette {
+        born: Rgb(230, 255, 150),
+        old: Rgb(20, 130, 40),
+        trail: Rgb(120, 90, 50),
+    };
+
+    // None for dead cells whose trail has faded out
+    fn color(&self, age: Age) -> Option<Rgb> {
+        match age {
+            Age::Alive(age) => {
+                let t = age.min(AGE_GRADIENT) as f64 / AGE_GRADIENT as f64;
+                Some(self.born.mix(self.old, t))
+            }
+            Age::Dead(age) if age < TRAIL_LENGTH => {
+                let t = (age + 1) as f64 / (TRAIL_LENGTH + 1) as f64;
+                Some(self.trail.mix(Rgb(0, 0, 0), t))
+            }
+            Age::Dead(_) => None,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::HEAT
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    // A palette name, or the born, old and trail colours such as fff0a0,c81e1e,5a3ca0
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "heat" => Ok(Self::HEAT),
+            "ocean" => Ok(Self::OCEAN),
+            "forest" => Ok(Self::FOREST),
+            custom => match custom.split(',').collect::<Vec<_>>().as_slice() {
+                [born, old, trail] => Ok(Palette {
+                    born: born.parse()?,
+                    old: old.parse()?,
+                    trail: trail.parse()?,
+                }),
+                _ => Err(
+                    "expected heat, ocean, forest or three hex colours BORN,OLD,TRAIL".to_string(),
+                ),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    // Every cell looks the same, for terminals without colours
+    #[default]
+    Monochrome,
+    Ansi256,
+    TrueColor,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Colors {
+    pub mode: ColorMode,
+    pub palette: Palette,
+}
+
+impl Colors {
+    pub fn is_enabled(&self) -> bool {
+        self.mode != ColorMode::Monochrome
+    }
+
+    // Escape sequence that switches the foreground to the colour of a cell of that age,
+    // None when the cell should not be drawn in colour
+    pub fn escape(&self, age: Age) -> Option<String> {
+        let color = self.palette.color(age)?;
+        match self.mode {
+            ColorMode::Monochrome => None,
+            ColorMode::Ansi256 => Some(format!("\x1b[38;5;{}m", color.ansi_256())),
+            ColorMode::TrueColor => Some(format!("\x1b[38;2;{};{};{}m", color.0, color.1, color.2)),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Life 1.05 and Life 1.06 patterns, see https://conwaylife.com/wiki/Life_1.05
+// and https://conwaylife.com/wiki/Life_1.06
+use super::{Pattern, PatternError};
+use crate::rule::Rule;
+use crate::Coord;
+
+pub const LIFE_105_HEADER: &str = "#Life 1.05";
+pub const LIFE_106_HEADER: &str = "#Life 1.06";
+
+// Life 1.05 rows should not be longer than this, wider patterns are split into several blocks
+const MAX_BLOCK_WIDTH: u32 = 80;
+
+fn parse_number(number: Option<&str>, line_number: usize, line: &str) -> Result<i32, PatternError> {
+    let number = number.ok_or_else(|| {
+        PatternError::new(line_number, line.len() + 1, "expected two coordinates")
+    })?;
+    let column = number.as_ptr() as usize - line.as_ptr() as usize + 1;
+    number.parse().map_err(|err| {
+        PatternError::new(
+            line_number,
+            column,
+            format!("invalid coordinate `{}`: {}", number, err),
+        )
+    })
+}
+
+// Smallest and largest coordinates of the cells read so far. Patterns are moved to (0, 0) once
+// they are read, so they must fit between 0 and i32::MAX
+#[derive(Default)]
+struct Bounds(Option<(Coord, Coord)>);
+
+impl Bounds {
+    // Fails when the cell makes the pattern too wide or too tall
+    fn add(
+        &mut self,
+        coord: &Coord,
+        line_number: usize,
+        column: usize,
+    ) -> Result<(), PatternError> {
+        let (min, max) = match &self.0 {
+            Some((min, max)) => (
+                Coord {
+                    x: min.x.min(coord.x),
+                    y: min.y.min(coord.y),
+                },
+                Coord {
+                    x: max.x.max(coord.x),
+                    y: max.y.max(coord.y),
+                },
+            ),
+            None => (coord.clone(), coord.clone()),
+        };
+        let span = |min: i32, max: i32| max as i64 - min as i64;
+        if span(min.x, max.x) >= i32::MAX as i64 || span(min.y, max.y) >= i32::MAX as i64 {
+            return Err(PatternError::new(
+                line_number,
+                column,
+                format!("pattern can be at most {} cells across", i32::MAX),
+            ));
+        }
+        self.0 = Some((min, max));
+        Ok(())
+    }
+}
+
+// Cells are given relative to an arbitrary centre, so the pattern is cropped to its bounding box
+fn cropped(pattern: Pattern, cells: Vec<Coord>) -> Pattern {
+    Pattern {
+        name: pattern.name,
+        comments: pattern.comments,
+        ..Pattern::from_cells(pattern.rule, cells)
+    }
+}
+
+pub fn parse_106(text: &str) -> Result<Pattern, PatternError> {
+    let mut cells = Vec::new();
+    let mut bounds = Bounds::default();
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut numbers = line.split_whitespace();
+        let x = parse_number(numbers.next(), line_number, line)?;
+        let y = parse_number(numbers.next(), line_number, line)?;
+        if let Some(extra) = numbers.next() {
+            let column = extra.as_ptr() as usize - line.as_ptr() as usize + 1;
+            return Err(PatternError::new(
+                line_number,
+                column,
+                format!("unexpected `{}` after the coordinates", extra),
+            ));
+        }
+        let coord = Coord { x, y };
+        bounds.add(&coord, line_number, 1)?;
+        cells.push(coord);
+    }
+    Ok(cropped(Pattern::default(), cells))
+}
+
+pub fn parse_105(text: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut cells = Vec::new();
+    let mut bounds = Bounds::default();
+    // Top left corner of the current block and the row within it
+    let mut block = (0, 0);
+    let mut row = 0;
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        if let Some(directive) = line.strip_prefix('#') {
+            let mut chars = directive.chars();
+            let kind = chars.next();
+            let rest = chars.as_str().trim();
+            match kind {
+                Some('D') => pattern.comments.push(rest.to_string()),
+                Some('N') => pattern.rule = Some(Rule::conway()),
+                Some('R') => {
+                    pattern.rule = Some(rest.parse::<Rule>().map_err(|err| {
+                        PatternError::new(line_number, 4, format!("invalid rule: {}", err))
+                    })?)
+                }
+                Some('P') => {
+                    let mut numbers = rest.split_whitespace();
+                    let x = parse_number(numbers.next(), line_number, line)?;
+                    let y = parse_number(numbers.next(), line_number, line)?;
+                    block = (x, y);
+                    row = 0;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (block_x, block_y) = block;
+        for (x, ch) in line.trim_end().chars().enumerate() {
+            match ch {
+                '.' => {}
+                '*' | 'O' => {
+                    let coord = i32::try_from(x)
+                        .ok()
+                        .and_then(|x| block_x.checked_add(x))
+                        .zip(block_y.checked_add(row))
+                        .map(|(x, y)| Coord { x, y })
+                        .ok_or_else(|| {
+                            PatternError::new(
+                                line_number,
+                                x + 1,
+                                "cell is off the edge of the coordinate space",
+                            )
+                        })?;
+                    bounds.add(&coord, line_number, x + 1)?;
+                    cells.push(coord);
+                }
+                _ => {
+                    return Err(PatternError::new(
+                        line_number,
+                        x + 1,
+                        format!("unexpected character `{}`, expected `.` or `*`", ch),
+                    ))
+                }
+            }
+        }
+        row += 1;
+    }
+    Ok(cropped(pattern, cells))
+}
+
+pub fn write_106(pattern: &Pattern) -> String {
+    let mut text = format!("{}\n", LIFE_106_HEADER);
+    for coord in &pattern.cells {
+        text.push_str(&format!("{} {}\n", coord.x, coord.y));
+    }
+    text
+}
+
+pub fn write_105(pattern: &Pattern) -> String {
+    let mut text = format!("{}\n", LIFE_105_HEADER);
+    for line in pattern.name.iter().chain(&pattern.comments) {
+        text.push_str(&format!("#D {}\n", line));
+    }
+    match pattern.rule {
+        None => {}
+        Some(rule) if rule == Rule::conway() => text.push_str("#N\n"),
+        Some(rule) => text.push_str(&format!("#R {}\n", rule.survival_birth_notation())),
+    }
+    for block_x in (0..pattern.width).step_by(MAX_BLOCK_WIDTH as usize) {
+        let block_width = MAX_BLOCK_WIDTH.min(pattern.width - block_x);
+        let mut rows = vec![vec!['.'; block_width as usize]; pattern.height as usize];
+        let block_cells = pattern
+            .cells
+            .iter()
+            .filter(|coord| (block_x..block_x + block_width).contains(&(coord.x as u32)));
+        for coord in block_cells {
+            rows[coord.y as usize][(coord.x as u32 - block_x) as usize] = '*';
+        }
+        text.push_str(&format!("#P {} 0\n", block_x));
+        for row in rows {
+            text.extend(row);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider() -> Pattern {
+        let cells = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| Coord { x, y });
+        Pattern::from_cells(Some(Rule::conway()), cells.to_vec())
+    }
+
+    #[test]
+    fn round_trips_life_105() {
+        let mut pattern = glider();
+        pattern.comments = vec!["A glider".to_string()];
+        let text = write_105(&pattern);
+        assert_eq!(text, "#Life 1.05\n#D A glider\n#N\n#P 0 0\n.*.\n..*\n***\n");
+        assert_eq!(parse_105(&text).unwrap(), pattern);
+        // Rules other than Conway's are written in survival/birth notation
+        pattern.rule = Some("B36/S23".parse().unwrap());
+        assert!(write_105(&pattern).contains("\n#R 23/36\n"));
+        assert_eq!(parse_105(&write_105(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn splits_wide_life_105_patterns_into_blocks() {
+        let cells = vec![Coord { x: 0, y: 0 }, Coord { x: 100, y: 1 }];
+        let pattern = Pattern::from_cells(None, cells);
+        let text = write_105(&pattern);
+        assert!(text.contains("\n#P 0 0\n") && text.contains("\n#P 80 0\n"));
+        assert_eq!(parse_105(&text).unwrap(), pattern);
+    }
+
+    #[test]
+    fn round_trips_life_106() {
+        let pattern = Pattern {
+            rule: None,
+            ..glider()
+        };
+        let text = write_106(&pattern);
+        assert_eq!(text, "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n");
+        assert_eq!(parse_106(&text).unwrap(), pattern);
+        // Cells around an arbitrary centre are moved to (0, 0)
+        let moved = parse_106("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(moved, pattern);
+    }
+
+    #[test]
+    fn points_at_errors() {
+        let error = |result: Result<Pattern, PatternError>| {
+            let err = result.unwrap_err();
+            (err.line, err.column)
+        };
+        assert_eq!(error(parse_106("#Life 1.06\n1 x\n")), (2, 3));
+        assert_eq!(error(parse_106("#Life 1.06\n1\n")), (2, 2));
+        assert_eq!(error(parse_106("#Life 1.06\n1 2 3\n")), (2, 5));
+        assert_eq!(error(parse_105("#Life 1.05\n#P 0 0\n.*x\n")), (3, 3));
+        assert_eq!(error(parse_105("#Life 1.05\n#R 23/9\n")), (2, 4));
+    }
+
+    #[test]
+    fn rejects_cells_off_the_coordinate_space() {
+        let err = parse_105("#Life 1.05\n#P 2147483647 0\n**\n").unwrap_err();
+        assert_eq!((err.line, err.column), (3, 2));
+        let err = parse_105("#Life 1.05\n#P 0 2147483647\n*\n*\n").unwrap_err();
+        assert_eq!((err.line, err.column), (4, 1));
+        let err = parse_106("#Life 1.06\n-2147483648 0\n2147483647 0\n").unwrap_err();
+        assert_eq!((err.line, err.column), (3, 1));
+        assert!(parse_105("#Life 1.05\n#P 2147483646 0\n*\n").is_ok());
+    }
+}
@@ -0,0 +1,320 @@
+// Macrocell patterns, see https://conwaylife.com/wiki/Macrocell
+// The file is the HashLife quadtree itself, so it is loaded straight into HashLife nodes
+// without ever expanding the pattern into individual cells.
+use std::collections::HashMap;
+
+use super::{Pattern, PatternError};
+use crate::hashlife::{HashLife, NodeId};
+use crate::rule::Rule;
+use crate::{Coord, Life};
+
+pub const HEADER: &str = "[M2]";
+
+// Leaves of the tree are 8x8 blocks of cells
+const LEAF_LEVEL: u8 = 3;
+const LEAF_SIZE: usize = 1 << LEAF_LEVEL;
+// Deepest tree accepted, already 2^64 cells across. Offsets within the tree are i128, which
+// leaves room for the levels hashlife adds while stepping
+const MAX_LEVEL: usize = 64;
+
+fn parse_leaf(
+    hashlife: &mut HashLife,
+    line: &str,
+    line_number: usize,
+) -> Result<NodeId, PatternError> {
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (column_index, ch) in line.chars().enumerate() {
+        let error = |message: &str| Err(PatternError::new(line_number, column_index + 1, message));
+        if y >= LEAF_SIZE as i32 {
+            return error("leaf has more than 8 rows");
+        }
+        match ch {
+            '.' | '*' if x >= LEAF_SIZE as i32 => return error("leaf row has more than 8 cells"),
+            '.' => x += 1,
+            '*' => {
+                cells.push(Coord { x, y });
+                x += 1;
+            }
+            '$' => {
+                x = 0;
+                y += 1;
+            }
+            _ => return error("unexpected character in a leaf, expected `.`, `*` or `$`"),
+        }
+    }
+    Ok(hashlife.build_node(LEAF_LEVEL, &cells))
+}
+
+fn parse_node(
+    hashlife: &mut HashLife,
+    nodes: &[NodeId],
+    line: &str,
+    line_number: usize,
+) -> Result<NodeId, PatternError> {
+    let mut fields = Vec::new();
+    for field in line.split_whitespace() {
+        let column = field.as_ptr() as usize - line.as_ptr() as usize + 1;
+        let number = field.parse::<usize>().map_err(|err| {
+            PatternError::new(
+                line_number,
+                column,
+                format!("invalid number `{}`: {}", field, err),
+            )
+        })?;
+        fields.push((number, column));
+    }
+    let [(level, level_column), children @ ..] = fields.as_slice() else {
+        return Err(PatternError::new(line_number, 1, "empty node"));
+    };
+    if children.len() != 4 {
+        return Err(PatternError::new(
+            line_number,
+            1,
+            "expected a level followed by four children",
+        ));
+    }
+    if *level <= LEAF_LEVEL as usize || *level > MAX_LEVEL {
+        let message = match *level {
+            1 => "multi-state macrocell patterns are not supported".to_string(),
+            level if level > MAX_LEVEL => {
+                format!(
+                    "level {} is too deep, it can be at most {}",
+                    level, MAX_LEVEL
+                )
+            }
+            level => format!("invalid level {}", level),
+        };
+        return Err(PatternError::new(line_number, *level_column, message));
+    }
+    let child_level = *level as u8 - 1;
+
+    let mut ids = [0; 4];
+    for (id, &(child, column)) in ids.iter_mut().zip(children) {
+        *id = match child {
+            0 => hashlife.empty_node(child_level),
+            child if child <= nodes.len() => nodes[child - 1],
+            child => {
+                return Err(PatternError::new(
+                    line_number,
+                    column,
+                    format!("node {} is used before it is defined", child),
+                ))
+            }
+        };
+        if hashlife.level(*id) != child_level {
+            return Err(PatternError::new(
+                line_number,
+                column,
+                format!("child should be a node of level {}", child_level),
+            ));
+        }
+    }
+    let [nw, ne, sw, se] = ids;
+    Ok(hashlife.join(nw, ne, sw, se))
+}
+
+// The pattern holds everything but the cells, which stay in the returned quadtree.
+// The top left corner of the live cells is placed at (0, 0) like for the other formats
+pub fn parse(text: &str) -> Result<(Pattern, HashLife), PatternError> {
+    let mut lines = text.lines().enumerate();
+    if !lines
+        .next()
+        .is_some_and(|(_, line)| line.starts_with(HEADER))
+    {
+        return Err(PatternError::new(1, 1, "missing `[M2]` header"));
+    }
+
+    let mut pattern = Pattern::default();
+    // The quadtree only holds the cells, the rule of the file is left to the caller in the
+    // pattern, as B0 rules can still run on a bounded board
+    let mut hashlife = HashLife::new(Rule::default()).unwrap();
+    // Node n of the file is nodes[n - 1], 0 always stands for an empty node
+    let mut nodes = Vec::new();
+    // Line of the last node, which is the root of the quadtree
+    let mut root_line = 1;
+    for (line_index, line) in lines {
+        let line_number = line_index + 1;
+        let line = line.trim_end();
+        if let Some(directive) = line.strip_prefix('#') {
+            let mut chars = directive.chars();
+            let kind = chars.next();
+            let rest = chars.as_str().trim();
+            match kind {
+                Some('R') => {
+                    let rule = rest.parse::<Rule>().map_err(|err| {
+                        PatternError::new(line_number, 4, format!("invalid rule: {}", err))
+                    })?;
+                    pattern.rule = Some(rule);
+                }
+                Some('N') => pattern.name = Some(rest.to_string()),
+                Some('C') => pattern.comments.push(rest.to_string()),
+                _ => {}
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let node = if line.starts_with(|ch: char| ch.is_ascii_digit()) {
+            parse_node(&mut hashlife, &nodes, line, line_number)?
+        } else {
+            parse_leaf(&mut hashlife, line, line_number)?
+        };
+        nodes.push(node);
+        root_line = line_number;
+    }
+
+    let root = match nodes.last() {
+        Some(&root) => root,
+        None => hashlife.empty_node(LEAF_LEVEL),
+    };
+    hashlife.set_root(root, 0, 0);
+    if let Some((min_x, min_y, max_x, max_y)) = hashlife.bounding_box() {
+        hashlife.translate(-min_x, -min_y);
+        let (width, height) = (max_x - min_x + 1, max_y - min_y + 1);
+        // Cells are addressed with i32 coordinates, so the pattern must fit between 0 and i32::MAX
+        (pattern.width, pattern.height) = match (i32::try_from(width), i32::try_from(height)) {
+            (Ok(width), Ok(height)) => (width as u32, height as u32),
+            _ => {
+                return Err(PatternError::new(
+                    root_line,
+                    1,
+                    format!(
+                        "pattern is {}x{} cells, it can be at most {} cells across",
+                        width,
+                        height,
+                        i32::MAX
+                    ),
+                ))
+            }
+        };
+    }
+    Ok((pattern, hashlife))
+}
+
+// Numbers every non empty node below id after its children, writing each one exactly once
+fn write_node(
+    hashlife: &HashLife,
+    id: NodeId,
+    numbers: &mut HashMap<NodeId, usize>,
+    lines: &mut Vec<String>,
+) -> usize {
+    if hashlife.node_population(id) == 0 {
+        return 0;
+    }
+    if let Some(&number) = numbers.get(&id) {
+        return number;
+    }
+    let level = hashlife.level(id);
+    let line = if level == LEAF_LEVEL {
+        let mut rows = vec![vec!['.'; LEAF_SIZE]; LEAF_SIZE];
+        for coord in hashlife.node_cells(id) {
+            rows[coord.y as usize][coord.x as usize] = '*';
+        }
+        let mut rows: Vec<String> = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .collect::<String>()
+                    .trim_end_matches('.')
+                    .to_string()
+            })
+            .collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        rows.into_iter().map(|row| row + "$").collect()
+    } else {
+        let children = hashlife
+            .children(id)
+            .map(|child| write_node(hashlife, child, numbers, lines));
+        format!(
+            "{} {} {} {} {}",
+            level, children[0], children[1], children[2], children[3]
+        )
+    };
+    lines.push(line);
+    numbers.insert(id, lines.len());
+    lines.len()
+}
+
+pub fn write(pattern: &Pattern, hashlife: &HashLife) -> String {
+    let rule = pattern.rule.unwrap_or(*hashlife.rule());
+    let mut text = format!("{} (gol)\n#R {}\n", HEADER, rule);
+    if let Some(name) = &pattern.name {
+        text.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        text.push_str(&format!("#C {}\n", comment));
+    }
+    let mut lines = Vec::new();
+    write_node(hashlife, hashlife.root(), &mut HashMap::new(), &mut lines);
+    for line in lines {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two cells on the diagonal of a level `level` node, one in each corner quadrant
+    fn diagonal(level: u8) -> String {
+        let mut text = format!("{} (gol)\n*$\n", HEADER);
+        for (node, level) in (LEAF_LEVEL + 1..=level).enumerate() {
+            text.push_str(&format!("{} {} 0 0 {}\n", level, node + 1, node + 1));
+        }
+        text
+    }
+
+    #[test]
+    fn measures_the_pattern() {
+        let (pattern, hashlife) = parse(&diagonal(12)).unwrap();
+        assert_eq!(hashlife.population(), 1 << 9);
+        assert_eq!((pattern.width, pattern.height), (4089, 4089));
+    }
+
+    // A completely filled tree of the given level
+    fn filled(level: u8) -> String {
+        let mut text = format!("{} (gol)\n{}\n", HEADER, "********$".repeat(LEAF_SIZE));
+        for (node, level) in (LEAF_LEVEL + 1..=level).enumerate() {
+            let child = node + 1;
+            text.push_str(&format!(
+                "{} {} {} {} {}\n",
+                level, child, child, child, child
+            ));
+        }
+        text
+    }
+
+    #[test]
+    fn measures_dense_patterns() {
+        let (pattern, hashlife) = parse(&filled(10)).unwrap();
+        assert_eq!(hashlife.population(), 1 << 20);
+        assert_eq!((pattern.width, pattern.height), (1024, 1024));
+        // Far more cells than a u64 population can count, still reported as too wide
+        let err = parse(&filled(40)).unwrap_err();
+        assert_eq!((err.line, err.column), (39, 1));
+    }
+
+    #[test]
+    fn rejects_trees_that_are_too_deep() {
+        assert!(parse(&diagonal(MAX_LEVEL as u8)).is_err());
+        let err = parse(&diagonal(MAX_LEVEL as u8 + 1)).unwrap_err();
+        assert_eq!((err.line, err.column), (MAX_LEVEL, 1));
+        assert!(err.message.contains("too deep"));
+        let err = parse(&format!("{}\n300 0 0 0 0\n", HEADER)).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 1));
+    }
+
+    #[test]
+    fn rejects_patterns_wider_than_coordinates_reach() {
+        // The error points at the root node, on the last line
+        let err = parse(&diagonal(33)).unwrap_err();
+        assert_eq!((err.line, err.column), (32, 1));
+        assert!(parse(&diagonal(31)).is_ok());
+    }
+}
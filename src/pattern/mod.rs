@@ -0,0 +1,219 @@
+// Reading and writing of the pattern file formats used by other Life programs
+pub mod life;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+
+use std::fmt;
+use std::path::Path;
+
+use crate::hashlife::HashLife;
+use crate::rule::Rule;
+use crate::{Coord, Life};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<Rule>,
+    // Declared size of the pattern, every live cell lies within it
+    pub width: u32,
+    pub height: u32,
+    // Live cells relative to the top left corner of the pattern
+    pub cells: Vec<Coord>,
+}
+
+impl Pattern {
+    // Pattern of the given live cells, cropped to their bounding box
+    pub fn from_cells(rule: Option<Rule>, mut cells: Vec<Coord>) -> Self {
+        let min_x = cells.iter().map(|coord| coord.x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|coord| coord.y).min().unwrap_or(0);
+        let max_x = cells.iter().map(|coord| coord.x).max().unwrap_or(-1);
+        let max_y = cells.iter().map(|coord| coord.y).max().unwrap_or(-1);
+        cells.sort_by_key(|coord| (coord.y, coord.x));
+        cells.dedup();
+        Self {
+            rule,
+            width: (max_x as i64 - min_x as i64 + 1) as u32,
+            height: (max_y as i64 - min_y as i64 + 1) as u32,
+            cells: cells
+                .into_iter()
+                .map(|coord| Coord {
+                    x: coord.x - min_x,
+                    y: coord.y - min_y,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    // Live cells moved so that the top left corner of the pattern lands on offset, without the
+    // ones that would fall off the edges of the coordinate space
+    pub fn cells_at(&self, offset: &Coord) -> impl Iterator<Item = Coord> + '_ {
+        let offset = offset.clone();
+        self.cells
+            .iter()
+            .filter_map(move |coord| offset.checked_step(coord.x, coord.y))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+    Macrocell,
+}
+
+impl Format {
+    // Guesses the format from the contents of a pattern file
+    pub fn detect(text: &str) -> Self {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let Some(first) = lines.clone().next() else {
+            return Format::Rle;
+        };
+        if first.starts_with(life::LIFE_105_HEADER) {
+            return Format::Life105;
+        }
+        if first.starts_with(life::LIFE_106_HEADER) {
+            return Format::Life106;
+        }
+        if first.starts_with(macrocell::HEADER) {
+            return Format::Macrocell;
+        }
+        if first.starts_with('!') {
+            return Format::Plaintext;
+        }
+        let is_rle_header = |line: &str| {
+            line.strip_prefix('x')
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        };
+        if lines.any(|line| !line.starts_with('#') && is_rle_header(line)) {
+            return Format::Rle;
+        }
+        let is_plaintext_row = |line: &str| line.chars().all(|ch| matches!(ch, '.' | 'O' | '*'));
+        if text.lines().map(str::trim).all(is_plaintext_row) {
+            return Format::Plaintext;
+        }
+        Format::Rle
+    }
+
+    // Format implied by the extension of a file name, if any
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rle" => Some(Format::Rle),
+            "cells" | "txt" => Some(Format::Plaintext),
+            "lif" | "life" => Some(Format::Life106),
+            "mc" => Some(Format::Macrocell),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Result<Pattern, PatternError> {
+        match self {
+            Format::Rle => rle::parse(text),
+            Format::Plaintext => plaintext::parse(text),
+            Format::Life105 => life::parse_105(text),
+            Format::Life106 => life::parse_106(text),
+            // Expands the quadtree, see macrocell::parse to keep it as is
+            Format::Macrocell => {
+                let (pattern, hashlife) = macrocell::parse(text)?;
+                Ok(Pattern {
+                    cells: hashlife.live_cells(),
+                    ..pattern
+                })
+            }
+        }
+    }
+
+    pub fn write(&self, pattern: &Pattern) -> String {
+        match self {
+            Format::Rle => rle::write(pattern),
+            Format::Plaintext => plaintext::write(pattern),
+            Format::Life105 => life::write_105(pattern),
+            Format::Life106 => life::write_106(pattern),
+            Format::Macrocell => {
+                // The rule is written from the pattern, the quadtree only holds the cells
+                let cells = pattern.cells.iter().cloned();
+                let hashlife = HashLife::from_iter(Rule::default(), cells).unwrap();
+                macrocell::write(pattern, &hashlife)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternError {
+    // Both start from 1, like in text editors
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl PatternError {
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_from_their_contents() {
+        let detect = Format::detect;
+        assert_eq!(detect("#N Glider\nx = 3, y = 3\nbo$2bo$3o!"), Format::Rle);
+        assert_eq!(detect("x=3,y=3\nbo$2bo$3o!"), Format::Rle);
+        assert_eq!(detect("\n#Life 1.05\n#P 0 0\n.*\n"), Format::Life105);
+        assert_eq!(detect("#Life 1.06\n0 0\n"), Format::Life106);
+        assert_eq!(detect("[M2] (golly 4.0)\n$$*$\n"), Format::Macrocell);
+        assert_eq!(detect("!Name: Glider\n.O.\n..O\nOOO\n"), Format::Plaintext);
+        assert_eq!(detect(".O.\n..O\nOOO\n"), Format::Plaintext);
+        // Anything else is left to the RLE parser to complain about
+        assert_eq!(detect("bo$2bo$3o!"), Format::Rle);
+        assert_eq!(detect(""), Format::Rle);
+    }
+
+    #[test]
+    fn detects_formats_from_extensions() {
+        let from_path = |path: &str| Format::from_path(Path::new(path));
+        assert_eq!(from_path("gun.RLE"), Some(Format::Rle));
+        assert_eq!(from_path("gun.cells"), Some(Format::Plaintext));
+        assert_eq!(from_path("gun.lif"), Some(Format::Life106));
+        assert_eq!(from_path("gun.mc"), Some(Format::Macrocell));
+        assert_eq!(from_path("gun"), None);
+    }
+
+    #[test]
+    fn round_trips_every_format() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| Coord { x, y });
+        let pattern = Pattern::from_cells(Some(Rule::conway()), glider.to_vec());
+        for format in [
+            Format::Rle,
+            Format::Plaintext,
+            Format::Life105,
+            Format::Life106,
+            Format::Macrocell,
+        ] {
+            let text = format.write(&pattern);
+            assert_eq!(Format::detect(&text), format);
+            let parsed = Format::detect(&text).parse(&text).unwrap();
+            assert_eq!(parsed.cells, pattern.cells, "{:?}", format);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Plaintext patterns (.cells), see https://conwaylife.com/wiki/Plaintext
+use super::{Pattern, PatternError};
+use crate::Coord;
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+    for (line_index, line) in text.lines().enumerate() {
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+        let row = line.trim_end();
+        for (x, ch) in row.chars().enumerate() {
+            match ch {
+                '.' => {}
+                'O' | '*' => pattern.cells.push(Coord { x: x as i32, y }),
+                _ => {
+                    return Err(PatternError::new(
+                        line_index + 1,
+                        x + 1,
+                        format!("unexpected character `{}`, expected `.` or `O`", ch),
+                    ))
+                }
+            }
+        }
+        pattern.width = pattern.width.max(row.chars().count() as u32);
+        y += 1;
+    }
+    // Blank lines at the end are usually just a trailing newline, not dead rows
+    let last_row = pattern.cells.iter().map(|coord| coord.y + 1).max();
+    pattern.height = last_row.unwrap_or(0).max(0) as u32;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut text = String::new();
+    if let Some(name) = &pattern.name {
+        text.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        text.push_str(&format!("!{}\n", comment));
+    }
+    let width = pattern.width as usize;
+    let mut rows = vec![vec!['.'; width]; pattern.height as usize];
+    for coord in &pattern.cells {
+        rows[coord.y as usize][coord.x as usize] = 'O';
+    }
+    for row in rows {
+        text.extend(row);
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let text = "!Name: Glider\n!A comment\n.O.\n..O\nOOO\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, ["A comment"]);
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells.len(), 5);
+        assert_eq!(write(&pattern), text);
+    }
+
+    #[test]
+    fn keeps_empty_rows_inside_the_pattern() {
+        let pattern = parse("O\n\n*.\n\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (2, 3));
+        assert_eq!(write(&pattern), "O.\n..\nO.\n");
+    }
+
+    #[test]
+    fn points_at_errors() {
+        let err = parse("!Name: x\n.O.\n.Ox\n").unwrap_err();
+        assert_eq!((err.line, err.column), (3, 3));
+    }
+}
@@ -0,0 +1,380 @@
+// Run Length Encoded patterns, see https://conwaylife.com/wiki/Run_Length_Encoded
+use super::{Pattern, PatternError};
+use crate::rule::Rule;
+use crate::Coord;
+
+// Lines of the pattern body are wrapped before they get longer than this
+const MAX_LINE_LENGTH: usize = 70;
+
+// Splits "key = value" pairs of the header line, keeping the column each value starts at
+fn parse_header(pattern: &mut Pattern, line: &str, line_number: usize) -> Result<(), PatternError> {
+    let (mut width, mut height) = (None, None);
+    let mut offset = 0;
+    while offset <= line.len() {
+        let column = offset + 1;
+        let rest = &line[offset..];
+        let key_column = column + rest.len() - rest.trim_start().len();
+        let (key, value) = match rest.split_once('=') {
+            Some((key, value)) if !key.contains(',') => (key, value),
+            _ => {
+                return Err(PatternError::new(
+                    line_number,
+                    key_column,
+                    format!(
+                        "expected `key = value` in the header, found `{}`",
+                        rest.split(',').next().unwrap_or(rest).trim()
+                    ),
+                ))
+            }
+        };
+        // Golly appends the bounded grid to the rule after a colon, e.g. B3/S23:T100,100, so
+        // the rule takes the rest of the line
+        let value = match key.trim() {
+            "rule" => value,
+            _ => value.split(',').next().unwrap_or(value),
+        };
+        offset += key.len() + 1 + value.len() + 1;
+        let value_column = column + key.len() + 1 + (value.len() - value.trim_start().len());
+        let value = value.trim();
+        // Cells are addressed with i32 coordinates, so the pattern must fit between 0 and i32::MAX
+        let size = |value: &str| {
+            let error = |reason: String| {
+                PatternError::new(
+                    line_number,
+                    value_column,
+                    format!("invalid size `{}`: {}", value, reason),
+                )
+            };
+            match value.parse::<u32>() {
+                Ok(size) if size <= i32::MAX as u32 => Ok(size),
+                Ok(_) => Err(error(format!("can be at most {}", i32::MAX))),
+                Err(err) => Err(error(err.to_string())),
+            }
+        };
+        match key.trim() {
+            "x" => width = Some(size(value)?),
+            "y" => height = Some(size(value)?),
+            "rule" => {
+                let rule = value.split(':').next().unwrap_or(value);
+                pattern.rule = Some(rule.trim().parse::<Rule>().map_err(|err| {
+                    PatternError::new(line_number, value_column, format!("invalid rule: {}", err))
+                })?);
+            }
+            key => {
+                return Err(PatternError::new(
+                    line_number,
+                    key_column,
+                    format!("unknown header key `{}`", key),
+                ))
+            }
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) => {
+            pattern.width = width;
+            pattern.height = height;
+            Ok(())
+        }
+        _ => Err(PatternError::new(
+            line_number,
+            1,
+            "header must declare both `x` and `y`",
+        )),
+    }
+}
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut has_header = false;
+    let (mut x, mut y) = (0u32, 0u32);
+    let mut run: Option<u32> = None;
+    // Multi-state patterns name states above 24 with two letters, such as pA
+    let mut state_prefix = None;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let trimmed = line.trim();
+        if !has_header {
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                let mut chars = comment.chars();
+                let kind = chars.next();
+                let text = chars.as_str().trim().to_string();
+                match kind {
+                    Some('N') => pattern.name = Some(text),
+                    Some('C' | 'c' | 'O') => pattern.comments.push(text),
+                    Some('r') => {
+                        pattern.rule = Some(text.parse::<Rule>().map_err(|err| {
+                            PatternError::new(line_number, 3, format!("invalid rule: {}", err))
+                        })?)
+                    }
+                    // Other lines such as #P offsets only matter to the program that wrote them
+                    _ => {}
+                }
+                continue;
+            }
+            if !trimmed.starts_with('x') {
+                return Err(PatternError::new(
+                    line_number,
+                    1,
+                    "expected a `x = .., y = ..` header before the pattern",
+                ));
+            }
+            parse_header(&mut pattern, line, line_number)?;
+            has_header = true;
+            continue;
+        }
+
+        for (column_index, ch) in line.chars().enumerate() {
+            let column = column_index + 1;
+            let error = |message: String| Err(PatternError::new(line_number, column, message));
+            let beyond_size = || {
+                error(format!(
+                    "cells extend beyond the declared size of {}x{}",
+                    pattern.width, pattern.height
+                ))
+            };
+            if let Some(prefix) = state_prefix {
+                if !ch.is_ascii_uppercase() || ch > 'X' {
+                    return error(format!("expected a state letter A-X after `{}`", prefix));
+                }
+            }
+            let alive = match ch {
+                _ if ch.is_whitespace() => continue,
+                '0'..='9' => {
+                    let digit = ch.to_digit(10).unwrap();
+                    let next = run.unwrap_or(0).checked_mul(10);
+                    run = match next.and_then(|run| run.checked_add(digit)) {
+                        Some(run) => Some(run),
+                        None => return error("run count is too large".to_string()),
+                    };
+                    continue;
+                }
+                'p'..='y' if state_prefix.is_none() => {
+                    state_prefix = Some(ch);
+                    continue;
+                }
+                '!' => {
+                    if run.is_some() {
+                        return error("run count is not followed by a cell".to_string());
+                    }
+                    return Ok(pattern);
+                }
+                // Moving just past the last row is fine as long as no cells follow
+                '$' => {
+                    y = match y.checked_add(run.take().unwrap_or(1)) {
+                        Some(y) if y <= pattern.height => y,
+                        _ => return beyond_size(),
+                    };
+                    x = 0;
+                    continue;
+                }
+                'b' | '.' => false,
+                'o' | 'A'..='X' => true,
+                _ => return error(format!("unexpected character `{}`", ch)),
+            };
+            state_prefix = None;
+            let count = run.take().unwrap_or(1);
+            if x.saturating_add(count) > pattern.width || y >= pattern.height {
+                return beyond_size();
+            }
+            if alive {
+                pattern.cells.extend((x..x + count).map(|x| Coord {
+                    x: x as i32,
+                    y: y as i32,
+                }));
+            }
+            x += count;
+        }
+    }
+
+    if !has_header {
+        return Err(PatternError::new(1, 1, "missing `x = .., y = ..` header"));
+    }
+    let line = text.lines().count().max(1);
+    let column = text.lines().last().map_or(0, |last| last.chars().count()) + 1;
+    Err(PatternError::new(
+        line,
+        column,
+        "pattern does not end with `!`",
+    ))
+}
+
+// Appends a run of count tags, wrapping the line if it would grow too long
+fn push_run(body: &mut String, line_length: &mut usize, count: u32, tag: char) {
+    let run = match count {
+        0 => return,
+        1 => tag.to_string(),
+        count => format!("{}{}", count, tag),
+    };
+    if *line_length + run.len() > MAX_LINE_LENGTH {
+        body.push('\n');
+        *line_length = 0;
+    }
+    *line_length += run.len();
+    body.push_str(&run);
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut rle = String::new();
+    if let Some(name) = &pattern.name {
+        rle.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        rle.push_str(&format!("#C {}\n", comment));
+    }
+    rle.push_str(&format!(
+        "x = {}, y = {}, rule = {}\n",
+        pattern.width,
+        pattern.height,
+        pattern.rule.unwrap_or_default()
+    ));
+
+    let mut cells = pattern.cells.clone();
+    cells.sort_by_key(|coord| (coord.y, coord.x));
+    cells.dedup();
+
+    let mut body = String::new();
+    let mut line_length = 0;
+    let (mut x, mut y) = (0, 0);
+    // Runs of live cells are only flushed once the next dead cell or row shows up,
+    // and dead cells at the end of a row are never written
+    let mut alive_run = 0;
+    for coord in cells {
+        if coord.y != y {
+            push_run(&mut body, &mut line_length, alive_run, 'o');
+            push_run(&mut body, &mut line_length, (coord.y - y) as u32, '$');
+            alive_run = 0;
+            (x, y) = (0, coord.y);
+        }
+        if coord.x != x + alive_run as i32 {
+            push_run(&mut body, &mut line_length, alive_run, 'o');
+            push_run(
+                &mut body,
+                &mut line_length,
+                (coord.x - x) as u32 - alive_run,
+                'b',
+            );
+            alive_run = 0;
+            x = coord.x;
+        }
+        alive_run += 1;
+    }
+    push_run(&mut body, &mut line_length, alive_run, 'o');
+    push_run(&mut body, &mut line_length, 1, '!');
+    rle.push_str(&body);
+    rle.push('\n');
+    rle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rows: &[(i32, &[(i32, i32)])]) -> Vec<Coord> {
+        rows.iter()
+            .flat_map(|&(y, runs)| {
+                runs.iter()
+                    .flat_map(move |&(x, length)| (x..x + length).map(move |x| Coord { x, y }))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_write_and_parse() {
+        let mut pattern = Pattern::from_cells(
+            Some("B36/S23".parse().unwrap()),
+            cells(&[
+                (0, &[(0, 12), (27, 1)]),
+                (1, &[(3, 1)]),
+                // 11 empty rows
+                (13, &[(0, 1), (2, 10), (40, 3)]),
+            ]),
+        );
+        pattern.name = Some("Runs".to_string());
+        pattern.comments = vec!["multi-digit runs".to_string()];
+        let rle = write(&pattern);
+        assert_eq!(
+            rle,
+            "#N Runs\n#C multi-digit runs\nx = 43, y = 14, rule = B36/S23\n\
+             12o15bo$3bo12$ob10o28b3o!\n"
+        );
+        assert_eq!(parse(&rle).unwrap(), pattern);
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        // Every other cell alive, so the body is a long line of `ob` runs
+        let pattern = Pattern::from_cells(
+            None,
+            cells(&[(0, &[(0, 1)]), (2, &[(0, 1)])])
+                .into_iter()
+                .chain((0..100).map(|x| Coord { x: 2 * x, y: 1 }))
+                .collect(),
+        );
+        let rle = write(&pattern);
+        let body: Vec<&str> = rle.lines().skip(1).collect();
+        assert!(body.len() > 2);
+        assert!(body.iter().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(body[..body.len() - 1]
+            .iter()
+            .all(|line| line.len() > MAX_LINE_LENGTH - 2));
+        let parsed = parse(&rle).unwrap();
+        assert_eq!(parsed.cells, pattern.cells);
+        assert_eq!((parsed.width, parsed.height), (199, 3));
+    }
+
+    #[test]
+    fn round_trips_through_parse_and_write() {
+        let rle = "#N Gosper glider gun\nx = 36, y = 9, rule = B3/S23\n\
+                   24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b\n\
+                   obo$10bo5bo7bo$11bo3bo$12b2o!\n";
+        let pattern = parse(rle).unwrap();
+        assert_eq!(pattern.cells.len(), 36);
+        assert_eq!(write(&pattern), rle);
+    }
+
+    #[test]
+    fn parses_golly_bounded_grid_rules() {
+        let pattern = parse("x = 3, y = 1, rule = B36/S23:T100,100\n3o!\n").unwrap();
+        assert_eq!(pattern.rule, Some("B36/S23".parse().unwrap()));
+        assert_eq!(pattern.cells.len(), 3);
+        let pattern = parse("x=2,y=2,rule=B3/S23:P20,30\n2o$2o!").unwrap();
+        assert_eq!((pattern.width, pattern.height), (2, 2));
+    }
+
+    #[test]
+    fn points_at_errors() {
+        let error = |rle: &str| {
+            let err = parse(rle).unwrap_err();
+            (err.line, err.column)
+        };
+        // Unknown keys, values that are not key = value, sizes and rules
+        assert_eq!(error("x = 1, z = 1\no!"), (1, 8));
+        assert_eq!(error("x = 1, 100, y = 1\no!"), (1, 8));
+        assert_eq!(error("x = 1, y = many\no!"), (1, 12));
+        assert_eq!(error("x = 1, y = 3000000000\no!"), (1, 12));
+        assert_eq!(error("x = 1, y = 1, rule = B9/S23\no!"), (1, 22));
+        assert_eq!(error("x = 1\no!"), (1, 1));
+        assert_eq!(error("#C no header\n3o!"), (2, 1));
+        // Bodies
+        assert_eq!(error("x = 3, y = 1\n2o?!"), (2, 3));
+        assert_eq!(error("x = 3, y = 1\n4o!"), (2, 2));
+        assert_eq!(error("x = 3, y = 2\no$o$o!"), (2, 5));
+        assert_eq!(error("x = 3, y = 1\n3o2!"), (2, 4));
+        assert_eq!(error("x = 3, y = 1\n3o"), (2, 3));
+    }
+
+    #[test]
+    fn rejects_huge_runs_without_overflowing() {
+        let err = parse("x = 1, y = 1\n4294967299o!").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 10));
+        assert_eq!(err.message, "run count is too large");
+        let err = parse("x = 1, y = 1\n4294967295$4294967295$!").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 11));
+        let err = parse("x = 1, y = 1\n4294967295b!").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 11));
+    }
+}
@@ -0,0 +1,375 @@
+// Flicker free drawing: every frame is compared with the previous one and only the glyphs that
+// changed are rewritten, using cursor positioning escapes on the alternate screen buffer.
+use std::io::{self, Write};
+
+use crate::palette::Colors;
+use crate::terminal;
+use crate::{Age, Coord, Life};
+
+// How cells are turned into characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Renderer {
+    // Two columns per cell, `o ` for live cells
+    #[default]
+    Classic,
+    // One column per two cells stacked vertically, drawn with ▀ ▄ █
+    HalfBlock,
+    // One braille character per 2x4 cells
+    Braille,
+}
+
+impl Renderer {
+    // Cycles through the renderers, for switching while the simulation runs
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Classic => Self::HalfBlock,
+            Self::HalfBlock => Self::Braille,
+            Self::Braille => Self::Classic,
+        }
+    }
+
+    // Cells packed into every character, across and down
+    fn block(&self) -> (i32, i32) {
+        match self {
+            Self::Classic => (1, 1),
+            Self::HalfBlock => (1, 2),
+            Self::Braille => (2, 4),
+        }
+    }
+
+    // Glyph of a block of cells, is_alive(dx, dy) tells whether the cell dx across and dy down
+    // from the top left corner of the block is alive
+    fn glyph(&self, is_alive: impl Fn(i32, i32) -> bool) -> String {
+        match self {
+            Self::Classic if is_alive(0, 0) => "o ".to_string(),
+            Self::Classic => "  ".to_string(),
+            Self::HalfBlock => match (is_alive(0, 0), is_alive(0, 1)) {
+                (true, true) => "█",
+                (true, false) => "▀",
+                (false, true) => "▄",
+                (false, false) => " ",
+            }
+            .to_string(),
+            Self::Braille => {
+                // Cell of each bit, see https://en.wikipedia.org/wiki/Braille_Patterns
+                const DOTS: [(i32, i32); 8] = [
+                    (0, 0),
+                    (0, 1),
+                    (0, 2),
+                    (1, 0),
+                    (1, 1),
+                    (1, 2),
+                    (0, 3),
+                    (1, 3),
+                ];
+                let dots = DOTS
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (dx, dy))| is_alive(*dx, *dy))
+                    .fold(0, |dots, (bit, _)| dots | 1 << bit);
+                char::from_u32(0x2800 + dots).unwrap().to_string()
+            }
+        }
+    }
+
+    // Age that decides the colour of a character: its youngest live cell, or when it has none,
+    // the cell that died most recently. Engines that do not track ages show every cell as old
+    fn block_age(&self, life: &dyn Life, cells: &[Coord]) -> Option<Age> {
+        let ages = cells.iter().map(|cell| match life.age(cell) {
+            Some(age) => age,
+            None if life.is_alive(cell) => Age::Alive(u32::MAX),
+            None => Age::Dead(u32::MAX),
+        });
+        ages.min_by_key(|age| match age {
+            Age::Alive(age) => (false, *age),
+            Age::Dead(age) => (true, *age),
+        })
+    }
+
+    // Width and height of the part of the viewport that fits on the terminal, along with the
+    // border and the two status lines under it. All of it when the terminal size is unknown
+    pub fn visible_size(&self, life: &dyn Life) -> (u32, u32) {
+        let (_, width, height) = life.viewport();
+        let Some((columns, rows)) = terminal::size() else {
+            return (width, height);
+        };
+        let (block_width, block_height) = self.block();
+        // Classic cells take two columns and its border three, the others have a border of two
+        let across = match self {
+            Self::Classic => (columns as u32).saturating_sub(3) / 2,
+            _ => (columns as u32).saturating_sub(2) * block_width as u32,
+        };
+        let down = (rows as u32).saturating_sub(4) * block_height as u32;
+        (width.min(across.max(1)), height.min(down.max(1)))
+    }
+
+    // Adds the visible part of the viewport of life with a border around it to the frame. The
+    // character holding the editor's cursor, relative to the viewport, is highlighted
+    pub fn draw(&self, life: &dyn Life, colors: Colors, frame: &mut Frame, cursor: Option<&Coord>) {
+        let (origin, _, _) = life.viewport();
+        let (width, height) = self.visible_size(life);
+        let (block_width, block_height) = self.block();
+        let columns = width.div_ceil(block_width as u32) as usize;
+        let (top, side, bottom) = match self {
+            Self::Classic => {
+                let border = format!(" {}", "# ".repeat(columns + 1));
+                (border.clone(), ("# ", "#"), border)
+            }
+            _ => (
+                format!("┌{}┐", "─".repeat(columns)),
+                ("│", "│"),
+                format!("└{}┘", "─".repeat(columns)),
+            ),
+        };
+        let glyph_width = if *self == Self::Classic { 2 } else { 1 };
+
+        frame.push_text(&top);
+        frame.new_line();
+        for y in (0..height as i32).step_by(block_height as usize) {
+            frame.push_text(side.0);
+            for x in (0..width as i32).step_by(block_width as usize) {
+                let cells: Vec<Coord> = (0..block_height)
+                    .flat_map(|dy| (0..block_width).map(move |dx| (x + dx, y + dy)))
+                    .filter(|&(x, y)| x < width as i32 && y < height as i32)
+                    .map(|(x, y)| origin.step(x, y))
+                    .collect();
+                let mut glyph = self.glyph(|dx, dy| {
+                    let (x, y) = (x + dx, y + dy);
+                    x < width as i32 && y < height as i32 && life.is_alive(&origin.step(x, y))
+                });
+                let escape = colors.is_enabled().then(|| self.block_age(life, &cells));
+                if let Some(age) = escape.flatten() {
+                    if let Some(escape) = colors.escape(age) {
+                        if let Age::Dead(_) = age {
+                            glyph = if *self == Self::Classic { "· " } else { "·" }.to_string();
+                        }
+                        glyph = format!("{}{}\x1b[0m", escape, glyph);
+                    }
+                }
+                let has_cursor = cursor.is_some_and(|cursor| {
+                    cursor.x / block_width == x / block_width
+                        && cursor.y / block_height == y / block_height
+                });
+                if has_cursor {
+                    frame.push(format!("\x1b[7m{}\x1b[0m", glyph), glyph_width);
+                } else {
+                    frame.push(glyph, glyph_width);
+                }
+            }
+            frame.push_text(side.1);
+            frame.new_line();
+        }
+        frame.push_text(&bottom);
+        frame.new_line();
+    }
+
+    // Cell of the viewport under a mouse click, the top left one when a character holds several
+    pub fn cell_at(&self, column: u16, row: u16, width: u32, height: u32) -> Option<Coord> {
+        let (block_width, block_height) = self.block();
+        // Both layouts have a border row on top, classic borders take two columns
+        let (first_column, glyph_width) = match self {
+            Self::Classic => (3, 2),
+            _ => (2, 1),
+        };
+        let x = (column as i32 - first_column).div_euclid(glyph_width) * block_width;
+        let y = (row as i32 - 2) * block_height;
+        ((0..width as i64).contains(&(x as i64)) && (0..height as i64).contains(&(y as i64)))
+            .then_some(Coord { x, y })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Glyph {
+    // May contain escape sequences, which take no room on the screen
+    text: String,
+    // Number of terminal columns the glyph covers
+    width: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Frame {
+    lines: Vec<Vec<Glyph>>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self {
+            lines: vec![Vec::new()],
+        }
+    }
+
+    pub fn push(&mut self, text: impl Into<String>, width: usize) {
+        let text = text.into();
+        self.lines.last_mut().unwrap().push(Glyph { text, width });
+    }
+
+    // Plain text without escape sequences, one column per char
+    pub fn push_text(&mut self, text: &str) {
+        self.push(text, text.chars().count());
+    }
+
+    pub fn new_line(&mut self) {
+        self.lines.push(Vec::new());
+    }
+}
+
+// Owns the alternate screen until dropped, which brings back the normal screen and the cursor
+pub struct Screen {
+    previous: Frame,
+}
+
+impl Screen {
+    pub fn enter() -> Self {
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        Self {
+            previous: Frame::default(),
+        }
+    }
+
+    pub fn draw(&mut self, frame: Frame) -> io::Result<()> {
+        let out = changes(&self.previous, &frame);
+        self.previous = frame;
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+// Escape sequences turning the previous frame on the screen into the next one
+fn changes(previous: &Frame, frame: &Frame) -> String {
+    let mut out = String::new();
+    for (row, line) in frame.lines.iter().enumerate() {
+        let previous = previous.lines.get(row);
+        let same_layout = previous.is_some_and(|previous| {
+            previous.len() == line.len()
+                && previous
+                    .iter()
+                    .zip(line)
+                    .all(|(previous, glyph)| previous.width == glyph.width)
+        });
+        if !same_layout {
+            out.push_str(&format!("\x1b[{};1H", row + 1));
+            line.iter().for_each(|glyph| out.push_str(&glyph.text));
+            out.push_str("\x1b[K");
+            continue;
+        }
+        // Writing a glyph leaves the cursor right after it, so runs of changed glyphs only
+        // need to be positioned once
+        let mut column = 0;
+        let mut cursor_column = None;
+        for (glyph, previous) in line.iter().zip(previous.unwrap()) {
+            if glyph != previous {
+                if cursor_column != Some(column) {
+                    out.push_str(&format!("\x1b[{};{}H", row + 1, column + 1));
+                }
+                out.push_str(&glyph.text);
+                cursor_column = Some(column + glyph.width);
+            }
+            column += glyph.width;
+        }
+    }
+    for row in frame.lines.len()..previous.lines.len() {
+        out.push_str(&format!("\x1b[{};1H\x1b[K", row + 1));
+    }
+    out
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A frame with a glyph for every char, where `o` and `.` are classic cells of two columns
+    fn frame(lines: &[&str]) -> Frame {
+        let mut frame = Frame::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                frame.new_line();
+            }
+            for ch in line.chars() {
+                match ch {
+                    'o' => frame.push("o ", 2),
+                    '.' => frame.push("  ", 2),
+                    ch => frame.push_text(&ch.to_string()),
+                }
+            }
+        }
+        frame
+    }
+
+    // Glyph of a block whose live cells are listed as (dx, dy)
+    fn glyph(renderer: Renderer, alive: &[(i32, i32)]) -> String {
+        renderer.glyph(|dx, dy| alive.contains(&(dx, dy)))
+    }
+
+    #[test]
+    fn classic_cells_take_two_columns() {
+        assert_eq!(glyph(Renderer::Classic, &[(0, 0)]), "o ");
+        assert_eq!(glyph(Renderer::Classic, &[]), "  ");
+    }
+
+    #[test]
+    fn half_blocks_stack_two_cells() {
+        assert_eq!(glyph(Renderer::HalfBlock, &[(0, 0), (0, 1)]), "█");
+        assert_eq!(glyph(Renderer::HalfBlock, &[(0, 0)]), "▀");
+        assert_eq!(glyph(Renderer::HalfBlock, &[(0, 1)]), "▄");
+        assert_eq!(glyph(Renderer::HalfBlock, &[]), " ");
+    }
+
+    #[test]
+    fn braille_dots_follow_the_unicode_numbering() {
+        // Dots 1 to 3 go down the left column, 4 to 6 down the right one, 7 and 8 are below
+        assert_eq!(glyph(Renderer::Braille, &[]), "\u{2800}");
+        assert_eq!(glyph(Renderer::Braille, &[(0, 0)]), "\u{2801}");
+        assert_eq!(glyph(Renderer::Braille, &[(0, 2)]), "\u{2804}");
+        assert_eq!(glyph(Renderer::Braille, &[(1, 0)]), "\u{2808}");
+        assert_eq!(glyph(Renderer::Braille, &[(0, 3)]), "\u{2840}");
+        assert_eq!(glyph(Renderer::Braille, &[(1, 3)]), "\u{2880}");
+        let all: Vec<(i32, i32)> = (0..4).flat_map(|y| [(0, y), (1, y)]).collect();
+        assert_eq!(glyph(Renderer::Braille, &all), "\u{28ff}");
+    }
+
+    #[test]
+    fn draws_whole_lines_the_first_time() {
+        assert_eq!(
+            changes(&Frame::default(), &frame(&["#o.#", "ab"])),
+            "\x1b[1;1H#o   #\x1b[K\x1b[2;1Hab\x1b[K"
+        );
+    }
+
+    #[test]
+    fn rewrites_only_changed_glyphs() {
+        let previous = frame(&["#oo..o#", "step 1"]);
+        let next = frame(&["#.....#", "step 2"]);
+        // The run of two changed cells is positioned once, the cell after the gap again
+        assert_eq!(
+            changes(&previous, &next),
+            "\x1b[1;2H    \x1b[1;10H  \x1b[2;6H2"
+        );
+        assert_eq!(changes(&next, &next), "");
+    }
+
+    #[test]
+    fn redraws_lines_whose_layout_changed() {
+        // Longer status lines have more glyphs, and glyphs of another width move the rest
+        let previous = frame(&["#o#", "paused"]);
+        let next = frame(&["#ab#", "paused!"]);
+        assert_eq!(
+            changes(&previous, &next),
+            "\x1b[1;1H#ab#\x1b[K\x1b[2;1Hpaused!\x1b[K"
+        );
+    }
+
+    #[test]
+    fn clears_rows_that_are_no_longer_used() {
+        let previous = frame(&["#o#", "status", "help"]);
+        let next = frame(&["#o#"]);
+        assert_eq!(changes(&previous, &next), "\x1b[2;1H\x1b[K\x1b[3;1H\x1b[K");
+    }
+}
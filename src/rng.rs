@@ -0,0 +1,267 @@
+// Pseudo random number generators for soups. They only use wrapping 64 bit arithmetic, so a
+// seed gives the same soup on every platform.
+use std::fmt;
+use std::str::FromStr;
+
+pub trait Rng {
+    fn next_u32(&mut self) -> u32;
+
+    fn next_u64(&mut self) -> u64 {
+        (self.next_u32() as u64) << 32 | self.next_u32() as u64
+    }
+
+    // Uniform in 0..bound, with the few values that would favour small results rejected.
+    // Stolen from https://arxiv.org/abs/1805.10941
+    fn below(&mut self, bound: u32) -> u32 {
+        let bound = bound.max(1);
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = self.next_u32() as u64 * bound as u64;
+            if product as u32 >= threshold {
+                return (product >> 32) as u32;
+            }
+        }
+    }
+}
+
+// Stolen from https://github.com/tsoding/carrotson/blob/master/carrotson.rs
+pub struct LCG {
+    state: u64,
+}
+
+impl LCG {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl Rng for LCG {
+    fn next_u32(&mut self) -> u32 {
+        // Stolen from https://en.wikipedia.org/wiki/Linear_congruential_generator
+        // Using the values of MMIX by Donald Knuth
+        const RAND_A: u64 = 6364136223846793005;
+        const RAND_C: u64 = 1442695040888963407;
+        self.state = self.state.wrapping_mul(RAND_A).wrapping_add(RAND_C);
+        (self.state >> 32) as u32
+    }
+}
+
+// Spreads the bits of a seed, so similar seeds still give unrelated states.
+// Stolen from https://prng.di.unimi.it/splitmix64.c
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// Stolen from https://prng.di.unimi.it/xoshiro256starstar.c
+pub struct Xoshiro256 {
+    state: [u64; 4],
+}
+
+impl Xoshiro256 {
+    pub fn new(seed: u64) -> Self {
+        let mut seed = seed;
+        Self {
+            state: [(); 4].map(|_| splitmix64(&mut seed)),
+        }
+    }
+}
+
+impl Rng for Xoshiro256 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+}
+
+// Stolen from https://www.pcg-random.org/download.html, pcg32 on stream 54 like the demo program
+// of the reference implementation
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const STREAM: u64 = 54;
+
+    pub fn new(seed: u64) -> Self {
+        let mut pcg = Self {
+            state: 0,
+            increment: Self::STREAM << 1 | 1,
+        };
+        pcg.next_u32();
+        pcg.state = pcg.state.wrapping_add(seed);
+        pcg.next_u32();
+        pcg
+    }
+}
+
+impl Rng for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Generator {
+    // Fast, but its low bits repeat after a few steps
+    Lcg,
+    #[default]
+    Xoshiro256,
+    Pcg32,
+}
+
+impl Generator {
+    pub fn seeded(self, seed: u64) -> Box<dyn Rng> {
+        match self {
+            Self::Lcg => Box::new(LCG::new(seed)),
+            Self::Xoshiro256 => Box::new(Xoshiro256::new(seed)),
+            Self::Pcg32 => Box::new(Pcg32::new(seed)),
+        }
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Lcg => write!(f, "lcg"),
+            Self::Xoshiro256 => write!(f, "xoshiro256"),
+            Self::Pcg32 => write!(f, "pcg32"),
+        }
+    }
+}
+
+// A number, or any other text that gets hashed into one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Seed {
+    Number(u64),
+    Text(String),
+}
+
+impl Seed {
+    // Changes every nanosecond, so runs started together still get different soups
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        Self::Number(nanos as u64)
+    }
+
+    pub fn value(&self) -> u64 {
+        match self {
+            Self::Number(seed) => *seed,
+            Self::Text(text) => fnv1a(text.as_bytes()),
+        }
+    }
+}
+
+// Stolen from http://www.isthe.com/chongo/tech/comp/fnv/, 64 bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(seed) => write!(f, "{}", seed),
+            Self::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl FromStr for Seed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(seed) => Ok(Self::Number(seed)),
+            Err(_) if s.is_empty() => Err("expected a number or some text".to_string()),
+            Err(_) => Ok(Self::Text(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lcg_follows_mmix() {
+        let mut lcg = LCG::new(0);
+        assert_eq!(
+            [lcg.next_u32(), lcg.next_u32(), lcg.next_u32()],
+            [335903614, 436792849, 2599843874]
+        );
+    }
+
+    // Outputs of the reference implementation for the state {1, 2, 3, 4}
+    #[test]
+    fn xoshiro256_matches_the_reference() {
+        let mut xoshiro = Xoshiro256 {
+            state: [1, 2, 3, 4],
+        };
+        assert_eq!(
+            [(); 4].map(|_| xoshiro.next_u64()),
+            [11520, 0, 1509978240, 1215971899390074240]
+        );
+    }
+
+    // Outputs of pcg32-demo from the reference implementation, seeded with 42 on stream 54
+    #[test]
+    fn pcg32_matches_the_reference() {
+        let mut pcg = Pcg32::new(42);
+        assert_eq!(
+            [pcg.next_u32(), pcg.next_u32(), pcg.next_u32()],
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330]
+        );
+    }
+
+    #[test]
+    fn fnv1a_matches_the_reference() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+        assert_eq!(Seed::Text("a".to_string()).value(), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn below_stays_below_the_bound() {
+        let mut rng = Xoshiro256::new(42);
+        assert!((0..1000).all(|_| rng.below(7) < 7));
+        assert_eq!(rng.below(1), 0);
+        assert_eq!(rng.below(0), 0);
+    }
+
+    #[test]
+    fn parses_seeds() {
+        assert_eq!("42".parse(), Ok(Seed::Number(42)));
+        assert_eq!("glider".parse(), Ok(Seed::Text("glider".to_string())));
+        assert!("".parse::<Seed>().is_err());
+    }
+}
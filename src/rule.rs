@@ -0,0 +1,304 @@
+// Outer-totalistic rules in the usual B/S notation, see https://conwaylife.com/wiki/Rulestring
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Rule {
+    // Bit n is set when a cell with n live neighbors is born / survives
+    birth: u16,
+    survival: u16,
+}
+
+impl Rule {
+    pub fn new(birth: &[usize], survival: &[usize]) -> Result<Self, RuleParseError> {
+        let mask = |counts: &[usize]| {
+            counts.iter().try_fold(0u16, |mask, &count| match count {
+                0..=8 => Ok(mask | (1 << count)),
+                _ => Err(RuleParseError::CountOutOfRange { count }),
+            })
+        };
+        Ok(Self {
+            birth: mask(birth)?,
+            survival: mask(survival)?,
+        })
+    }
+
+    pub fn conway() -> Self {
+        Self::new(&[3], &[2, 3]).unwrap()
+    }
+
+    pub fn is_born(&self, num_live_neighbors: usize) -> bool {
+        self.birth & (1 << num_live_neighbors) != 0
+    }
+
+    pub fn survives(&self, num_live_neighbors: usize) -> bool {
+        self.survival & (1 << num_live_neighbors) != 0
+    }
+
+    pub fn next_alive(&self, alive: bool, num_live_neighbors: usize) -> bool {
+        if alive {
+            self.survives(num_live_neighbors)
+        } else {
+            self.is_born(num_live_neighbors)
+        }
+    }
+
+    // Older survival/birth notation, e.g. 23/3 for Conway's Game of Life
+    pub fn survival_birth_notation(&self) -> String {
+        let counts = |mask: u16| {
+            (0..=8)
+                .filter(|n| mask & (1 << n) != 0)
+                .map(|n| n.to_string())
+                .collect::<String>()
+        };
+        format!("{}/{}", counts(self.survival), counts(self.birth))
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        for n in (0..=8).filter(|&n| self.is_born(n)) {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for n in (0..=8).filter(|&n| self.survives(n)) {
+            write!(f, "{}", n)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleParseError {
+    Empty,
+    MissingSeparator,
+    TooManySeparators,
+    MissingPrefix { part: String },
+    // Both parts start with B, or both with S
+    DuplicatePart { prefix: char },
+    InvalidCount { part: String, ch: char },
+    DuplicateCount { part: String, count: usize },
+    CountOutOfRange { count: usize },
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "rulestring is empty"),
+            Self::MissingSeparator => {
+                write!(
+                    f,
+                    "rulestring must have two parts separated by '/', e.g. B3/S23"
+                )
+            }
+            Self::TooManySeparators => write!(f, "rulestring has more than one '/'"),
+            Self::MissingPrefix { part } => {
+                write!(
+                    f,
+                    "`{}` must start with B or S when the other part does",
+                    part
+                )
+            }
+            Self::DuplicatePart { prefix } => write!(
+                f,
+                "rulestring has two {} parts, expected one B and one S part, e.g. B3/S23",
+                prefix
+            ),
+            Self::InvalidCount { part, ch } => write!(
+                f,
+                "`{}` contains `{}`, neighbor counts must be digits between 0 and 8",
+                part, ch
+            ),
+            Self::DuplicateCount { part, count } => {
+                write!(
+                    f,
+                    "`{}` lists neighbor count {} more than once",
+                    part, count
+                )
+            }
+            Self::CountOutOfRange { count } => write!(
+                f,
+                "neighbor count {} is out of range, a cell has at most 8 neighbors",
+                count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuleParseError {}
+
+fn parse_counts(part: &str, digits: &str) -> Result<u16, RuleParseError> {
+    let mut mask = 0u16;
+    for ch in digits.chars() {
+        let count = match ch.to_digit(10) {
+            Some(count) if count <= 8 => count as usize,
+            _ => {
+                return Err(RuleParseError::InvalidCount {
+                    part: part.to_string(),
+                    ch,
+                })
+            }
+        };
+        if mask & (1 << count) != 0 {
+            return Err(RuleParseError::DuplicateCount {
+                part: part.to_string(),
+                count,
+            });
+        }
+        mask |= 1 << count;
+    }
+    Ok(mask)
+}
+
+impl FromStr for Rule {
+    type Err = RuleParseError;
+
+    // Accepts "B36/S23", "S23/B36" and the older survival-first "23/36" notation
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(RuleParseError::Empty);
+        }
+        let mut parts = s.split('/');
+        let (first, second) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(first), Some(second), None) => (first, second),
+            (_, None, _) => return Err(RuleParseError::MissingSeparator),
+            _ => return Err(RuleParseError::TooManySeparators),
+        };
+
+        let prefix = |part: &str| part.chars().next().map(|ch| ch.to_ascii_uppercase());
+        let (birth, survival) = match (prefix(first), prefix(second)) {
+            (Some('B'), Some('S')) => (first, second),
+            (Some('S'), Some('B')) => (second, first),
+            (Some(prefix @ ('B' | 'S')), Some(second_prefix)) if prefix == second_prefix => {
+                return Err(RuleParseError::DuplicatePart { prefix })
+            }
+            (Some('B' | 'S'), _) => {
+                return Err(RuleParseError::MissingPrefix {
+                    part: second.to_string(),
+                })
+            }
+            (_, Some('B' | 'S')) => {
+                return Err(RuleParseError::MissingPrefix {
+                    part: first.to_string(),
+                })
+            }
+            // Legacy notation lists survival counts first, e.g. 23/3
+            _ => {
+                return Ok(Self {
+                    birth: parse_counts(second, second)?,
+                    survival: parse_counts(first, first)?,
+                })
+            }
+        };
+        Ok(Self {
+            birth: parse_counts(birth, &birth[1..])?,
+            survival: parse_counts(survival, &survival[1..])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(birth: &[usize], survival: &[usize]) -> Rule {
+        Rule::new(birth, survival).unwrap()
+    }
+
+    #[test]
+    fn parses_birth_survival_notation() {
+        assert_eq!("B3/S23".parse(), Ok(Rule::conway()));
+        assert_eq!("S23/B3".parse(), Ok(Rule::conway()));
+        assert_eq!("B36/S23".parse(), Ok(rule(&[3, 6], &[2, 3])));
+        assert_eq!(" B3/S23 ".parse(), Ok(Rule::conway()));
+    }
+
+    #[test]
+    fn parses_survival_birth_notation() {
+        assert_eq!("23/3".parse(), Ok(Rule::conway()));
+        assert_eq!("23/36".parse(), Ok(rule(&[3, 6], &[2, 3])));
+        assert_eq!(Rule::conway().survival_birth_notation(), "23/3");
+    }
+
+    #[test]
+    fn parses_lowercase() {
+        assert_eq!("b3/s23".parse(), Ok(Rule::conway()));
+        assert_eq!("s23/b3".parse(), Ok(Rule::conway()));
+    }
+
+    #[test]
+    fn parses_empty_sides() {
+        assert_eq!("B3/S".parse(), Ok(rule(&[3], &[])));
+        assert_eq!("B/S23".parse(), Ok(rule(&[], &[2, 3])));
+        assert_eq!("/3".parse(), Ok(rule(&[3], &[])));
+        assert_eq!("23/".parse(), Ok(rule(&[], &[2, 3])));
+    }
+
+    #[test]
+    fn displays_what_it_parses() {
+        for rulestring in ["B3/S23", "B36/S23", "B0/S8", "B/S"] {
+            assert_eq!(rulestring.parse::<Rule>().unwrap().to_string(), rulestring);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_rulestrings() {
+        assert_eq!("".parse::<Rule>(), Err(RuleParseError::Empty));
+        assert_eq!(
+            "B3S23".parse::<Rule>(),
+            Err(RuleParseError::MissingSeparator)
+        );
+        assert_eq!(
+            "B3/S23/C2".parse::<Rule>(),
+            Err(RuleParseError::TooManySeparators)
+        );
+        assert_eq!(
+            "B3/23".parse::<Rule>(),
+            Err(RuleParseError::MissingPrefix {
+                part: "23".to_string()
+            })
+        );
+        assert_eq!(
+            "B3/B23".parse::<Rule>(),
+            Err(RuleParseError::DuplicatePart { prefix: 'B' })
+        );
+        assert_eq!(
+            "s3/S23".parse::<Rule>(),
+            Err(RuleParseError::DuplicatePart { prefix: 'S' })
+        );
+        assert_eq!(
+            "B39/S23".parse::<Rule>(),
+            Err(RuleParseError::InvalidCount {
+                part: "B39".to_string(),
+                ch: '9'
+            })
+        );
+        assert_eq!(
+            "B33/S23".parse::<Rule>(),
+            Err(RuleParseError::DuplicateCount {
+                part: "B33".to_string(),
+                count: 3
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_counts_above_8() {
+        assert_eq!(
+            Rule::new(&[3], &[2, 9]),
+            Err(RuleParseError::CountOutOfRange { count: 9 })
+        );
+        assert_eq!(
+            Rule::new(&[16], &[]),
+            Err(RuleParseError::CountOutOfRange { count: 16 })
+        );
+    }
+}
@@ -0,0 +1,325 @@
+// Random soups filling a region at an exact density, optionally symmetric like the soups of
+// apgsearch: a random choice of cells gets mirrored and rotated around the centre of the region.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::rng::Rng;
+use crate::Coord;
+
+// Maps a position relative to the centre of the region, in half cells, to its image
+type Transform = fn(i64, i64) -> (i64, i64);
+
+const IDENTITY: Transform = |x, y| (x, y);
+const ROTATE_90: Transform = |x, y| (-y, x);
+const ROTATE_180: Transform = |x, y| (-x, -y);
+const ROTATE_270: Transform = |x, y| (y, -x);
+const MIRROR_X: Transform = |x, y| (-x, y);
+const MIRROR_Y: Transform = |x, y| (x, -y);
+const DIAGONAL: Transform = |x, y| (y, x);
+const ANTI_DIAGONAL: Transform = |x, y| (-y, -x);
+
+// Named as in apgsearch. The digit tells where the centre sits: 1 on a cell, 2 on the middle of
+// an edge and 4 on a corner between cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    #[default]
+    C1,
+    C2_1,
+    C2_2,
+    C2_4,
+    C4_1,
+    C4_4,
+    D2Plus1,
+    D2Plus2,
+    D2X,
+    D4Plus1,
+    D4Plus2,
+    D4Plus4,
+    D4X1,
+    D4X4,
+    D8_1,
+    D8_4,
+}
+
+const SYMMETRIES: &[(Symmetry, &str)] = &[
+    (Symmetry::C1, "C1"),
+    (Symmetry::C2_1, "C2_1"),
+    (Symmetry::C2_2, "C2_2"),
+    (Symmetry::C2_4, "C2_4"),
+    (Symmetry::C4_1, "C4_1"),
+    (Symmetry::C4_4, "C4_4"),
+    (Symmetry::D2Plus1, "D2_+1"),
+    (Symmetry::D2Plus2, "D2_+2"),
+    (Symmetry::D2X, "D2_x"),
+    (Symmetry::D4Plus1, "D4_+1"),
+    (Symmetry::D4Plus2, "D4_+2"),
+    (Symmetry::D4Plus4, "D4_+4"),
+    (Symmetry::D4X1, "D4_x1"),
+    (Symmetry::D4X4, "D4_x4"),
+    (Symmetry::D8_1, "D8_1"),
+    (Symmetry::D8_4, "D8_4"),
+];
+
+impl Symmetry {
+    fn transforms(self) -> &'static [Transform] {
+        match self {
+            Self::C1 => &[IDENTITY],
+            Self::C2_1 | Self::C2_2 | Self::C2_4 => &[IDENTITY, ROTATE_180],
+            Self::C4_1 | Self::C4_4 => &[IDENTITY, ROTATE_90, ROTATE_180, ROTATE_270],
+            Self::D2Plus1 | Self::D2Plus2 => &[IDENTITY, MIRROR_X],
+            Self::D2X => &[IDENTITY, DIAGONAL],
+            Self::D4Plus1 | Self::D4Plus2 | Self::D4Plus4 => {
+                &[IDENTITY, MIRROR_X, MIRROR_Y, ROTATE_180]
+            }
+            Self::D4X1 | Self::D4X4 => &[IDENTITY, DIAGONAL, ANTI_DIAGONAL, ROTATE_180],
+            Self::D8_1 | Self::D8_4 => &[
+                IDENTITY,
+                ROTATE_90,
+                ROTATE_180,
+                ROTATE_270,
+                MIRROR_X,
+                MIRROR_Y,
+                DIAGONAL,
+                ANTI_DIAGONAL,
+            ],
+        }
+    }
+
+    // Whether the width and height must be odd, None when either will do
+    fn odd_sides(self) -> (Option<bool>, Option<bool>) {
+        match self {
+            Self::C1 | Self::D2X => (None, None),
+            Self::C2_1 | Self::C4_1 | Self::D4Plus1 | Self::D4X1 | Self::D8_1 => {
+                (Some(true), Some(true))
+            }
+            Self::C2_2 | Self::D4Plus2 => (Some(true), Some(false)),
+            Self::C2_4 | Self::C4_4 | Self::D4Plus4 | Self::D4X4 | Self::D8_4 => {
+                (Some(false), Some(false))
+            }
+            Self::D2Plus1 => (Some(true), None),
+            Self::D2Plus2 => (Some(false), None),
+        }
+    }
+
+    // Rotations by 90 degrees and diagonal mirrors only map square regions onto themselves
+    fn is_square(self) -> bool {
+        matches!(
+            self,
+            Self::C4_1 | Self::C4_4 | Self::D2X | Self::D4X1 | Self::D4X4 | Self::D8_1 | Self::D8_4
+        )
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (_, name) = SYMMETRIES
+            .iter()
+            .find(|(symmetry, _)| symmetry == self)
+            .unwrap();
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (symmetry, _) = SYMMETRIES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = SYMMETRIES.iter().map(|(_, name)| *name).collect();
+                format!("expected one of {}", names.join(", "))
+            })?;
+        Ok(*symmetry)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Soup {
+    width: u32,
+    height: u32,
+    // Share of the cells that are alive, rounded to a whole number of cells
+    density: f64,
+    symmetry: Symmetry,
+}
+
+impl Soup {
+    pub fn new(width: u32, height: u32, density: f64) -> Self {
+        Self {
+            width,
+            height,
+            density: density.clamp(0.0, 1.0),
+            symmetry: Symmetry::default(),
+        }
+    }
+
+    pub fn with_symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    // Size of the region that gets filled, shrunk by a cell along the sides whose parity does
+    // not fit the centre of the symmetry, and to a square when the symmetry needs one
+    pub fn size(&self) -> (u32, u32) {
+        let (mut width, mut height) = (self.width.max(1), self.height.max(1));
+        if self.symmetry.is_square() {
+            width = width.min(height);
+            height = width;
+        }
+        let fit = |side: u32, odd: Option<bool>| match odd {
+            Some(odd) if (side % 2 == 1) != odd => {
+                side.checked_sub(1).filter(|&side| side > 0).unwrap_or(2)
+            }
+            _ => side,
+        };
+        let (odd_width, odd_height) = self.symmetry.odd_sides();
+        (fit(width, odd_width), fit(height, odd_height))
+    }
+
+    // Live cells of the region, with (0, 0) at its top left corner. Every cell is mapped onto
+    // the cells it is symmetric with, and the density is exact over these groups of cells
+    pub fn generate(&self, rng: &mut dyn Rng) -> Vec<Coord> {
+        let (width, height) = self.size();
+        let (center_x, center_y) = (width as i64 - 1, height as i64 - 1);
+        let orbit = |index: usize| -> Vec<usize> {
+            let (x, y) = (
+                (index % width as usize) as i64,
+                (index / width as usize) as i64,
+            );
+            let mut orbit: Vec<usize> = self
+                .symmetry
+                .transforms()
+                .iter()
+                .map(|transform| {
+                    let (x, y) = transform(2 * x - center_x, 2 * y - center_y);
+                    let (x, y) = ((x + center_x) / 2, (y + center_y) / 2);
+                    y as usize * width as usize + x as usize
+                })
+                .collect();
+            orbit.sort_unstable();
+            orbit.dedup();
+            orbit
+        };
+        // Each group of symmetric cells is picked through its first cell
+        let mut firsts: Vec<usize> = (0..width as usize * height as usize)
+            .filter(|&index| orbit(index)[0] == index)
+            .collect();
+        let alive = (self.density * firsts.len() as f64).round() as usize;
+        // Partial Fisher-Yates shuffle, so no cell is picked twice
+        for i in 0..alive {
+            let j = i + rng.below((firsts.len() - i) as u32) as usize;
+            firsts.swap(i, j);
+        }
+        let mut cells: Vec<usize> = firsts[..alive].iter().flat_map(|&i| orbit(i)).collect();
+        cells.sort_unstable();
+        cells
+            .into_iter()
+            .map(|index| Coord {
+                x: (index % width as usize) as i32,
+                y: (index / width as usize) as i32,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::LCG;
+    use std::collections::{BTreeSet, HashSet};
+
+    // Image of a cell of a width x height region under a transform around its centre
+    fn image(transform: Transform, (width, height): (u32, u32), coord: &Coord) -> Coord {
+        let (center_x, center_y) = (width as i64 - 1, height as i64 - 1);
+        let (x, y) = transform(2 * coord.x as i64 - center_x, 2 * coord.y as i64 - center_y);
+        Coord {
+            x: ((x + center_x) / 2) as i32,
+            y: ((y + center_y) / 2) as i32,
+        }
+    }
+
+    // Cells a cell is mapped onto by the symmetry, itself included
+    fn orbit(symmetry: Symmetry, size: (u32, u32), coord: &Coord) -> BTreeSet<(i32, i32)> {
+        symmetry
+            .transforms()
+            .iter()
+            .map(|&transform| image(transform, size, coord))
+            .map(|coord| (coord.x, coord.y))
+            .collect()
+    }
+
+    #[test]
+    fn fits_the_region_to_the_symmetry() {
+        for &(symmetry, _) in SYMMETRIES {
+            let (odd_width, odd_height) = symmetry.odd_sides();
+            for width in 1..12 {
+                for height in 1..12 {
+                    let size = Soup::new(width, height, 0.5).with_symmetry(symmetry).size();
+                    let context = format!("{} soup of {}x{}", symmetry, width, height);
+                    assert!(size.0 > 0 && size.1 > 0, "{}", context);
+                    assert!(
+                        size.0 <= width.max(2) && size.1 <= height.max(2),
+                        "{}",
+                        context
+                    );
+                    if symmetry.is_square() {
+                        assert_eq!(size.0, size.1, "{}", context);
+                    }
+                    if let Some(odd) = odd_width {
+                        assert_eq!(size.0 % 2 == 1, odd, "{}", context);
+                    }
+                    if let Some(odd) = odd_height {
+                        assert_eq!(size.1 % 2 == 1, odd, "{}", context);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn soups_are_invariant_under_their_symmetry() {
+        let mut rng = LCG::new(7);
+        for &(symmetry, _) in SYMMETRIES {
+            for (width, height) in [(9, 9), (10, 10), (9, 12), (12, 9)] {
+                let soup = Soup::new(width, height, 0.4).with_symmetry(symmetry);
+                let size = soup.size();
+                let cells: HashSet<Coord> = soup.generate(&mut rng).into_iter().collect();
+                for &transform in symmetry.transforms() {
+                    let moved: HashSet<Coord> = cells
+                        .iter()
+                        .map(|coord| image(transform, size, coord))
+                        .collect();
+                    assert_eq!(moved, cells, "{} soup of {}x{}", symmetry, width, height);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn picks_an_exact_share_of_the_symmetric_groups() {
+        let mut rng = LCG::new(11);
+        for &(symmetry, _) in SYMMETRIES {
+            for density in [0.0, 0.1, 0.37, 0.5, 1.0] {
+                let soup = Soup::new(11, 10, density).with_symmetry(symmetry);
+                let size = soup.size();
+                let orbits: HashSet<BTreeSet<(i32, i32)>> = (0..size.1 as i32)
+                    .flat_map(|y| (0..size.0 as i32).map(move |x| Coord { x, y }))
+                    .map(|coord| orbit(symmetry, size, &coord))
+                    .collect();
+                let alive: HashSet<BTreeSet<(i32, i32)>> = soup
+                    .generate(&mut rng)
+                    .iter()
+                    .map(|coord| orbit(symmetry, size, coord))
+                    .collect();
+                assert_eq!(
+                    alive.len(),
+                    (density * orbits.len() as f64).round() as usize,
+                    "{} soup at density {}",
+                    symmetry,
+                    density
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// Unbounded engine that only stores live cells, so patterns can grow for as long as they like.
+// Only the neighborhood of live cells is evaluated each generation, which means B0 rules
+// (where empty space comes alive) are not supported.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::rule::Rule;
+use crate::{Coord, Life};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SparseError {
+    // Empty cells with no live neighbors would come alive all over the plane at once
+    BirthOnZero(Rule),
+}
+
+impl fmt::Display for SparseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BirthOnZero(rule) => write!(
+                f,
+                "rule {} can not run on the sparse engine, B0 rules would fill the infinite plane",
+                rule
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SparseError {}
+
+#[derive(Debug, Clone)]
+pub struct SparseLife {
+    live: HashSet<Coord>,
+    rule: Rule,
+    // Part of the plane that gets drawn
+    origin: Coord,
+    width: u32,
+    height: u32,
+}
+
+impl SparseLife {
+    pub fn from_iter(
+        rule: Rule,
+        live_coords: impl Iterator<Item = Coord>,
+    ) -> Result<Self, SparseError> {
+        if rule.is_born(0) {
+            return Err(SparseError::BirthOnZero(rule));
+        }
+        Ok(Self {
+            live: live_coords.collect(),
+            rule,
+            origin: Coord { x: 0, y: 0 },
+            width: 15,
+            height: 15,
+        })
+    }
+
+    pub fn with_viewport(mut self, origin: Coord, width: u32, height: u32) -> Self {
+        self.origin = origin;
+        self.width = width;
+        self.height = height;
+        self
+    }
+}
+
+impl Life for SparseLife {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn is_alive(&self, coord: &Coord) -> bool {
+        self.live.contains(coord)
+    }
+
+    fn set_alive(&mut self, coord: &Coord, alive: bool) {
+        if alive {
+            self.live.insert(coord.clone());
+        } else {
+            self.live.remove(coord);
+        }
+    }
+
+    fn step(&mut self) {
+        let mut num_live_neighbors: HashMap<Coord, usize> = HashMap::new();
+        for coord in &self.live {
+            for neighbor in coord.neighbors() {
+                *num_live_neighbors.entry(neighbor).or_insert(0) += 1;
+            }
+        }
+
+        // Live cells without any live neighbors never show up in the counts
+        let isolated = self
+            .live
+            .iter()
+            .filter(|coord| !num_live_neighbors.contains_key(coord))
+            .filter(|_| self.rule.survives(0))
+            .cloned();
+
+        let next_live = num_live_neighbors
+            .iter()
+            .filter(|(coord, &count)| self.rule.next_alive(self.live.contains(coord), count))
+            .map(|(coord, _)| coord.clone())
+            .chain(isolated)
+            .collect();
+        self.live = next_live;
+    }
+
+    fn live_cells(&self) -> Vec<Coord> {
+        self.live.iter().cloned().collect()
+    }
+
+    fn viewport(&self) -> (Coord, u32, u32) {
+        (self.origin.clone(), self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_b0_rules() {
+        let rule: Rule = "B03/S23".parse().unwrap();
+        assert_eq!(
+            SparseLife::from_iter(rule, std::iter::empty()).err(),
+            Some(SparseError::BirthOnZero(rule))
+        );
+    }
+
+    #[test]
+    fn block_stays_in_the_corner_of_the_plane() {
+        let block: Vec<Coord> = [(i32::MAX - 1, i32::MAX - 1), (i32::MAX, i32::MAX - 1)]
+            .into_iter()
+            .flat_map(|(x, y)| [Coord { x, y }, Coord { x, y: y + 1 }])
+            .collect();
+        let mut life = SparseLife::from_iter(Rule::default(), block.clone().into_iter()).unwrap();
+        life.step();
+        let mut cells = life.live_cells();
+        cells.sort_by_key(|coord| (coord.x, coord.y));
+        assert_eq!(cells, block);
+    }
+
+    #[test]
+    fn glider_moves_diagonally() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let at = |dx, dy| {
+            let mut cells: Vec<Coord> = glider
+                .iter()
+                .map(|&(x, y)| Coord {
+                    x: x + dx,
+                    y: y + dy,
+                })
+                .collect();
+            cells.sort_by_key(|coord| (coord.y, coord.x));
+            cells
+        };
+        let mut life = SparseLife::from_iter(Rule::default(), at(0, 0).into_iter()).unwrap();
+        for _ in 0..4 {
+            life.step();
+        }
+        let mut cells = life.live_cells();
+        cells.sort_by_key(|coord| (coord.y, coord.x));
+        assert_eq!(cells, at(1, 1));
+    }
+}
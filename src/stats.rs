@@ -0,0 +1,231 @@
+// Statistics of every generation, written as CSV or JSON Lines while the simulation runs
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{Coord, Life};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Csv,
+    JsonLines,
+}
+
+impl StatsFormat {
+    // Guesses the format from the extension of a file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "json" => Some(Self::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Stats {
+    generation: u64,
+    population: usize,
+    births: usize,
+    deaths: usize,
+    // Top left and bottom right live cells, None once everything died
+    bounding_box: Option<(Coord, Coord)>,
+    // Share of the board that is alive, None on the infinite plane which has no size to share
+    density: Option<f64>,
+}
+
+impl Stats {
+    fn csv(&self) -> String {
+        let bounding_box = match &self.bounding_box {
+            Some((min, max)) => format!("{},{},{},{}", min.x, min.y, max.x, max.y),
+            None => ",,,".to_string(),
+        };
+        let density = match self.density {
+            Some(density) => format!("{:.6}", density),
+            None => String::new(),
+        };
+        format!(
+            "{},{},{},{},{},{}",
+            self.generation, self.population, self.births, self.deaths, bounding_box, density
+        )
+    }
+
+    fn json(&self) -> String {
+        let bounding_box = match &self.bounding_box {
+            Some((min, max)) => format!(
+                "{{\"min_x\":{},\"min_y\":{},\"max_x\":{},\"max_y\":{}}}",
+                min.x, min.y, max.x, max.y
+            ),
+            None => "null".to_string(),
+        };
+        let density = match self.density {
+            Some(density) => format!("{:.6}", density),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"generation\":{},\"population\":{},\"births\":{},\"deaths\":{},\"bounding_box\":{},\"density\":{}}}",
+            self.generation, self.population, self.births, self.deaths, bounding_box, density
+        )
+    }
+}
+
+pub struct StatsWriter {
+    out: Box<dyn Write>,
+    format: StatsFormat,
+    // Live cells of the previously recorded generation, to count births and deaths
+    previous: Option<HashSet<Coord>>,
+}
+
+impl StatsWriter {
+    pub fn new(mut out: Box<dyn Write>, format: StatsFormat) -> io::Result<Self> {
+        if format == StatsFormat::Csv {
+            writeln!(
+                out,
+                "generation,population,births,deaths,min_x,min_y,max_x,max_y,density"
+            )?;
+        }
+        Ok(Self {
+            out,
+            format,
+            previous: None,
+        })
+    }
+
+    // The first generation recorded, and the first one after the board is replaced, has no
+    // births or deaths
+    pub fn record(&mut self, generation: u64, life: &dyn Life) -> io::Result<()> {
+        let live: HashSet<Coord> = life.live_cells().into_iter().collect();
+        let (births, deaths) = match &self.previous {
+            Some(previous) => (
+                live.difference(previous).count(),
+                previous.difference(&live).count(),
+            ),
+            None => (0, 0),
+        };
+        let bounding_box = live
+            .iter()
+            .fold(None, |bounds: Option<(Coord, Coord)>, coord| {
+                Some(match bounds {
+                    Some((min, max)) => (
+                        Coord {
+                            x: min.x.min(coord.x),
+                            y: min.y.min(coord.y),
+                        },
+                        Coord {
+                            x: max.x.max(coord.x),
+                            y: max.y.max(coord.y),
+                        },
+                    ),
+                    None => (coord.clone(), coord.clone()),
+                })
+            });
+        let stats = Stats {
+            generation,
+            population: live.len(),
+            births,
+            deaths,
+            bounding_box,
+            density: life
+                .board_size()
+                .map(|(width, height)| live.len() as f64 / (width as f64 * height as f64).max(1.0)),
+        };
+        self.previous = Some(live);
+        let line = match self.format {
+            StatsFormat::Csv => stats.csv(),
+            StatsFormat::JsonLines => stats.json(),
+        };
+        writeln!(self.out, "{}", line)
+    }
+
+    // Forgets the previous generation, for when the board is replaced by another one
+    pub fn restart(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+    use crate::sparse::SparseLife;
+    use crate::GOL;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Output shared with the writer, so it can be read back after recording
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Lines written for a blinker over two generations, then for an empty board
+    fn record(format: StatsFormat, mut blinker: Box<dyn Life>) -> Vec<String> {
+        let output = Output::default();
+        let mut writer = StatsWriter::new(Box::new(output.clone()), format).unwrap();
+        writer.record(0, blinker.as_ref()).unwrap();
+        blinker.step();
+        writer.record(1, blinker.as_ref()).unwrap();
+        writer
+            .record(2, &GOL::from_iter(5, 5, std::iter::empty()))
+            .unwrap();
+        writer.finish().unwrap();
+        let text = String::from_utf8(output.0.borrow().clone()).unwrap();
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn blinker() -> impl Iterator<Item = Coord> {
+        (1..4).map(|x| Coord { x, y: 2 })
+    }
+
+    #[test]
+    fn writes_csv() {
+        let lines = record(StatsFormat::Csv, Box::new(GOL::from_iter(5, 5, blinker())));
+        assert_eq!(
+            lines,
+            [
+                "generation,population,births,deaths,min_x,min_y,max_x,max_y,density",
+                "0,3,0,0,1,2,3,2,0.120000",
+                "1,3,2,2,2,1,2,3,0.120000",
+                "2,0,0,3,,,,,0.000000",
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let lines = record(
+            StatsFormat::JsonLines,
+            Box::new(GOL::from_iter(5, 5, blinker())),
+        );
+        assert_eq!(
+            lines,
+            [
+                r#"{"generation":0,"population":3,"births":0,"deaths":0,"bounding_box":{"min_x":1,"min_y":2,"max_x":3,"max_y":2},"density":0.120000}"#,
+                r#"{"generation":1,"population":3,"births":2,"deaths":2,"bounding_box":{"min_x":2,"min_y":1,"max_x":2,"max_y":3},"density":0.120000}"#,
+                r#"{"generation":2,"population":0,"births":0,"deaths":3,"bounding_box":null,"density":0.000000}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_density_out_on_the_infinite_plane() {
+        let sparse = SparseLife::from_iter(Rule::default(), blinker()).unwrap();
+        let lines = record(StatsFormat::Csv, Box::new(sparse));
+        assert_eq!(lines[1], "0,3,0,0,1,2,3,2,");
+        let sparse = SparseLife::from_iter(Rule::default(), blinker()).unwrap();
+        let lines = record(StatsFormat::JsonLines, Box::new(sparse));
+        assert!(lines[0].ends_with(r#""density":null}"#));
+    }
+}
@@ -0,0 +1,317 @@
+// Raw keyboard input without external crates. Terminal attributes are changed through a minimal
+// termios FFI, the functions come from the C library that std already links against.
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+    // Left button press, 1 based like the terminal reports it
+    Click { column: u16, row: u16 },
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod sys {
+    use std::io;
+
+    #[cfg(target_os = "linux")]
+    mod flags {
+        pub type TcFlag = u32;
+        pub type Speed = u32;
+        pub const NCCS: usize = 32;
+        pub const ISIG: TcFlag = 0o1;
+        pub const ICANON: TcFlag = 0o2;
+        pub const ECHO: TcFlag = 0o10;
+        pub const VTIME: usize = 5;
+        pub const VMIN: usize = 6;
+        pub const TIOCGWINSZ: std::os::raw::c_ulong = 0x5413;
+    }
+
+    #[cfg(target_os = "macos")]
+    mod flags {
+        pub type TcFlag = u64;
+        pub type Speed = u64;
+        pub const NCCS: usize = 20;
+        pub const ISIG: TcFlag = 0x80;
+        pub const ICANON: TcFlag = 0x100;
+        pub const ECHO: TcFlag = 0x8;
+        pub const VMIN: usize = 16;
+        pub const VTIME: usize = 17;
+        pub const TIOCGWINSZ: std::os::raw::c_ulong = 0x40087468;
+    }
+
+    use flags::*;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct Termios {
+        iflag: TcFlag,
+        oflag: TcFlag,
+        cflag: TcFlag,
+        lflag: TcFlag,
+        #[cfg(target_os = "linux")]
+        line: u8,
+        cc: [u8; NCCS],
+        ispeed: Speed,
+        ospeed: Speed,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct WindowSize {
+        rows: u16,
+        columns: u16,
+        x_pixels: u16,
+        y_pixels: u16,
+    }
+
+    extern "C" {
+        fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        fn tcsetattr(fd: i32, action: i32, termios: *const Termios) -> i32;
+        fn ioctl(fd: i32, request: std::os::raw::c_ulong, ...) -> i32;
+    }
+
+    const STDIN: i32 = 0;
+    const STDOUT: i32 = 1;
+    const TCSANOW: i32 = 0;
+
+    // Columns and rows of the terminal on stdout, None when it is not a terminal
+    pub fn size() -> Option<(u16, u16)> {
+        let mut size = WindowSize::default();
+        // SAFETY: TIOCGWINSZ writes a winsize struct, which WindowSize mirrors
+        if unsafe { ioctl(STDOUT, TIOCGWINSZ, &mut size as *mut WindowSize) } != 0 {
+            return None;
+        }
+        (size.columns > 0 && size.rows > 0).then_some((size.columns, size.rows))
+    }
+
+    // Fails when stdin is not a terminal
+    pub fn get() -> io::Result<Termios> {
+        let mut termios = std::mem::MaybeUninit::<Termios>::uninit();
+        // SAFETY: tcgetattr fills the whole struct when it succeeds
+        if unsafe { tcgetattr(STDIN, termios.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { termios.assume_init() })
+    }
+
+    pub fn set(termios: &Termios) -> io::Result<()> {
+        // SAFETY: termios is a valid struct obtained from tcgetattr
+        if unsafe { tcsetattr(STDIN, TCSANOW, termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // Keys are delivered one at a time without echo, Ctrl-C arrives as a byte instead of a signal.
+    // Output processing is left alone so newlines still return the carriage
+    pub fn raw(termios: &Termios) -> Termios {
+        let mut raw = *termios;
+        raw.lflag &= !(ICANON | ECHO | ISIG);
+        raw.cc[VMIN] = 1;
+        raw.cc[VTIME] = 0;
+        raw
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod sys {
+    use std::io;
+
+    pub type Termios = ();
+
+    pub fn get() -> io::Result<Termios> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw terminal input is not supported on this platform",
+        ))
+    }
+
+    pub fn set(_termios: &Termios) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn size() -> Option<(u16, u16)> {
+        None
+    }
+
+    pub fn raw(_termios: &Termios) -> Termios {}
+}
+
+// Columns and rows of the terminal that gets drawn on, None when stdout is not a terminal
+pub fn size() -> Option<(u16, u16)> {
+    sys::size()
+}
+
+// Puts the terminal back the way it was found when dropped, even when unwinding from a panic
+struct RawMode {
+    original: sys::Termios,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let original = sys::get()?;
+        sys::set(&sys::raw(&original))?;
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = sys::set(&self.original);
+        print!("\x1b[?1000l\x1b[?1006l\x1b[?25h");
+    }
+}
+
+pub struct Keyboard {
+    _raw_mode: RawMode,
+    keys: Receiver<Key>,
+}
+
+impl Keyboard {
+    // Fails when stdin is not a terminal, e.g. when input is piped
+    pub fn open() -> io::Result<Self> {
+        let raw_mode = RawMode::enable()?;
+        let (sender, keys) = mpsc::channel();
+        // Reads block, so they happen on their own thread which lives until the program exits
+        std::thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut buffer = [0; 64];
+            while let Ok(read) = stdin.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                for key in parse_keys(&buffer[..read]) {
+                    if sender.send(key).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            _raw_mode: raw_mode,
+            keys,
+        })
+    }
+
+    // xterm mouse reporting in SGR encoding, so clicks arrive as keys
+    pub fn set_mouse_reporting(&self, enabled: bool) {
+        if enabled {
+            print!("\x1b[?1000h\x1b[?1006h");
+        } else {
+            print!("\x1b[?1000l\x1b[?1006l");
+        }
+    }
+
+    // Waits for the next key press, forever when there is no timeout. Once stdin is closed no
+    // key can come anymore, so the whole timeout is slept through instead
+    pub fn next_key(&self, timeout: Option<Duration>) -> Option<Key> {
+        match timeout {
+            Some(timeout) => match self.keys.recv_timeout(timeout) {
+                Ok(key) => Some(key),
+                Err(RecvTimeoutError::Disconnected) => {
+                    std::thread::sleep(timeout);
+                    None
+                }
+                Err(RecvTimeoutError::Timeout) => None,
+            },
+            None => self.keys.recv().ok(),
+        }
+    }
+}
+
+// Mouse reports look like `<0;12;5M`, button, column and row, M for presses and m for releases
+fn parse_mouse(report: &str) -> Option<Key> {
+    let (report, pressed) = match report.strip_suffix('M') {
+        Some(report) => (report, true),
+        None => (report.strip_suffix('m')?, false),
+    };
+    let mut fields = report.split(';').map(|field| field.parse::<u16>().ok());
+    let (button, column, row) = (fields.next()??, fields.next()??, fields.next()??);
+    (pressed && button == 0).then_some(Key::Click { column, row })
+}
+
+// Escape sequences of a key press always arrive together, so they are never split across reads
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut rest = text.as_ref();
+    let mut keys = Vec::new();
+    while let Some(ch) = rest.chars().next() {
+        rest = &rest[ch.len_utf8()..];
+        if ch != '\x1b' {
+            keys.push(Key::Char(ch));
+            continue;
+        }
+        let Some(sequence) = rest.strip_prefix('[') else {
+            keys.push(Key::Escape);
+            continue;
+        };
+        if let Some(report) = sequence.strip_prefix('<') {
+            let end = report.find(['M', 'm']).map_or(report.len(), |end| end + 1);
+            keys.extend(parse_mouse(&report[..end]));
+            rest = &report[end..];
+            continue;
+        }
+        // Control sequences end with a byte between @ and ~, such as the ~ of `[3~` for delete.
+        // Arrows are kept, even with modifiers as in `[1;5A`, every other key is dropped
+        let Some(end) = sequence.find(|ch| ('@'..='~').contains(&ch)) else {
+            rest = "";
+            continue;
+        };
+        keys.extend(match &sequence[end..end + 1] {
+            "A" => Some(Key::Up),
+            "B" => Some(Key::Down),
+            "C" => Some(Key::Right),
+            "D" => Some(Key::Left),
+            _ => None,
+        });
+        rest = &sequence[end + 1..];
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_characters_and_arrows() {
+        assert_eq!(
+            parse_keys(b"q \x1b[A\x1b[B\x1b[C\x1b[D"),
+            [
+                Key::Char('q'),
+                Key::Char(' '),
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left
+            ]
+        );
+        assert_eq!(parse_keys(b"\x1b[1;5A"), [Key::Up]);
+        assert_eq!(parse_keys(b"\x1b"), [Key::Escape]);
+        assert_eq!(parse_keys(b"\x1bq"), [Key::Escape, Key::Char('q')]);
+    }
+
+    #[test]
+    fn drops_other_control_sequences() {
+        assert_eq!(
+            parse_keys(b"a\x1b[3~b\x1b[15;2~\x1b[Hc"),
+            [Key::Char('a'), Key::Char('b'), Key::Char('c')]
+        );
+        assert_eq!(parse_keys(b"a\x1b[12"), [Key::Char('a')]);
+    }
+
+    #[test]
+    fn parses_mouse_clicks() {
+        assert_eq!(
+            parse_keys(b"\x1b[<0;12;5M\x1b[<0;12;5m\x1b[<2;3;4Mx"),
+            [Key::Click { column: 12, row: 5 }, Key::Char('x')]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+// Terminal front end: draws the board every generation and reacts to the keyboard in between
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::detect::{Cycle, CycleDetector};
+use crate::palette::Colors;
+use crate::render::{Frame, Renderer, Screen};
+use crate::stats::StatsWriter;
+use crate::terminal::{Key, Keyboard};
+use crate::{Coord, Life};
+
+const HELP: &str =
+    "space pause | n step | + faster | - slower | r reseed | v view | e edit | q quit";
+const EDIT_HELP: &str = "arrows move | space toggle | click toggle | e done | q quit";
+const MAX_DELAY: Duration = Duration::from_secs(10);
+// Generations remembered by the cycle detector
+const HISTORY: usize = 1024;
+
+// How the simulation is shown and when it stops
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub delay: Duration,
+    // Runs forever when there is no limit
+    pub generations: Option<u64>,
+    pub renderer: Renderer,
+    pub colors: Colors,
+    // Stop as soon as the board turns out to be static or periodic
+    pub until_stable: bool,
+}
+
+impl Settings {
+    fn is_finished(&self, run: &Run) -> bool {
+        self.generations
+            .is_some_and(|generations| run.generation >= generations)
+            || (self.until_stable && run.cycle.is_some())
+    }
+}
+
+// The board being simulated along with what is known about its history
+pub struct Run {
+    pub life: Box<dyn Life>,
+    pub generation: u64,
+    // Set once the board repeats itself
+    pub cycle: Option<Cycle>,
+    // Only looked for when somebody needs to know, as it goes through every live cell
+    detector: Option<CycleDetector>,
+    stats: Option<StatsWriter>,
+}
+
+impl Run {
+    fn new(life: Box<dyn Life>, stats: Option<StatsWriter>, detect: bool) -> io::Result<Self> {
+        let mut run = Self {
+            life,
+            generation: 0,
+            cycle: None,
+            detector: detect.then(|| CycleDetector::new(HISTORY)),
+            stats,
+        };
+        run.restart();
+        run.record_stats()?;
+        Ok(run)
+    }
+
+    fn record_stats(&mut self) -> io::Result<()> {
+        match &mut self.stats {
+            Some(stats) => stats.record(self.generation, self.life.as_ref()),
+            None => Ok(()),
+        }
+    }
+
+    fn step(&mut self) -> io::Result<()> {
+        self.life.step();
+        self.generation += 1;
+        if let (None, Some(detector)) = (self.cycle, &mut self.detector) {
+            self.cycle = detector.record(self.generation, self.life.as_ref());
+        }
+        self.record_stats()
+    }
+
+    // Carries on with another board from generation 0
+    fn replace(&mut self, life: Box<dyn Life>) -> io::Result<()> {
+        self.life = life;
+        self.generation = 0;
+        self.restart();
+        if let Some(stats) = &mut self.stats {
+            stats.restart();
+        }
+        self.record_stats()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match &mut self.stats {
+            Some(stats) => stats.finish(),
+            None => Ok(()),
+        }
+    }
+
+    // Starts looking for cycles from scratch, after the board was changed by hand
+    fn restart(&mut self) {
+        if let Some(detector) = &mut self.detector {
+            detector.clear();
+            self.cycle = detector.record(self.generation, self.life.as_ref());
+        }
+    }
+
+    fn toggle(&mut self, cursor: &Coord) {
+        let (origin, _, _) = self.life.viewport();
+        let coord = origin.step(cursor.x, cursor.y);
+        self.life.set_alive(&coord, !self.life.is_alive(&coord));
+        self.restart();
+    }
+}
+
+// Steps as fast as possible without drawing anything, until the generation limit or
+// stabilisation when asked for. Cycles are only looked for in the latter case
+pub fn run_headless(
+    life: Box<dyn Life>,
+    settings: Settings,
+    stats: Option<StatsWriter>,
+) -> io::Result<Run> {
+    let mut run = Run::new(life, stats, settings.until_stable)?;
+    while !settings.is_finished(&run) {
+        run.step()?;
+    }
+    run.finish()?;
+    Ok(run)
+}
+
+// Runs until the generation limit, stabilisation when asked for, or q. caption is shown under
+// the board, and reseed builds a new random soup when r is pressed. Fails when the
+// statistics can not be written
+pub fn simulate(
+    life: Box<dyn Life>,
+    mut caption: String,
+    settings: Settings,
+    stats: Option<StatsWriter>,
+    mut reseed: impl FnMut() -> (Box<dyn Life>, String),
+) -> io::Result<Run> {
+    let Settings {
+        mut delay,
+        mut renderer,
+        colors,
+        ..
+    } = settings;
+    // Without a terminal on stdin, e.g. when piped, the simulation runs without controls
+    let keyboard = Keyboard::open().ok();
+    let mut screen = Screen::enter();
+    // The status line shows the cycle once there is one
+    let mut run = Run::new(life, stats, true)?;
+    let mut paused = false;
+    let mut next_step = Instant::now() + delay;
+    // Position of the editor's cursor on the viewport while editing
+    let mut cursor: Option<Coord> = None;
+    loop {
+        let mut frame = Frame::new();
+        renderer.draw(run.life.as_ref(), colors, &mut frame, cursor.as_ref());
+        let mut status = format!("{} | generation {}", caption, run.generation);
+        if let Some(cycle) = run.cycle {
+            status.push_str(&format!(" | {}", cycle));
+        }
+        if cursor.is_some() {
+            status.push_str(" | editing");
+        } else if keyboard.is_some() {
+            status.push_str(&format!(" | delay {}ms", delay.as_millis()));
+            if paused {
+                status.push_str(" | paused");
+            }
+        }
+        frame.push_text(&status);
+        if keyboard.is_some() {
+            frame.new_line();
+            frame.push_text(if cursor.is_some() { EDIT_HELP } else { HELP });
+        }
+        // Nobody is watching anymore once stdout is gone
+        if screen.draw(frame).is_err() {
+            break;
+        }
+        if settings.is_finished(&run) {
+            break;
+        }
+
+        let Some(keyboard) = &keyboard else {
+            std::thread::sleep(delay);
+            run.step()?;
+            continue;
+        };
+        let timeout = (!paused).then(|| next_step.saturating_duration_since(Instant::now()));
+        let key = keyboard.next_key(timeout);
+        if let Some(position) = &mut cursor {
+            let (width, height) = renderer.visible_size(run.life.as_ref());
+            match key {
+                Some(Key::Up) => position.y = (position.y - 1).max(0),
+                Some(Key::Down) => position.y = (position.y + 1).min(height as i32 - 1),
+                Some(Key::Left) => position.x = (position.x - 1).max(0),
+                Some(Key::Right) => position.x = (position.x + 1).min(width as i32 - 1),
+                Some(Key::Char(' ' | '\r' | '\n')) => run.toggle(position),
+                Some(Key::Click { column, row }) => {
+                    if let Some(clicked) = renderer.cell_at(column, row, width, height) {
+                        run.toggle(&clicked);
+                        *position = clicked;
+                    }
+                }
+                Some(Key::Char('e') | Key::Escape) => {
+                    keyboard.set_mouse_reporting(false);
+                    cursor = None;
+                }
+                Some(Key::Char('q' | '\u{3}')) => break,
+                _ => {}
+            }
+            continue;
+        }
+        match key {
+            None if !paused => {
+                run.step()?;
+                next_step = Instant::now() + delay;
+            }
+            // Stdin was closed, so nothing could ever resume the simulation
+            None => paused = false,
+            Some(Key::Char(' ')) => {
+                paused = !paused;
+                next_step = Instant::now() + delay;
+            }
+            Some(Key::Char('n')) => {
+                paused = true;
+                run.step()?;
+            }
+            Some(Key::Char('+' | '=')) => delay = (delay / 2).max(Duration::from_millis(1)),
+            Some(Key::Char('-' | '_')) => {
+                delay = (delay * 2).clamp(Duration::from_millis(1), MAX_DELAY)
+            }
+            Some(Key::Char('v')) => renderer = renderer.next(),
+            Some(Key::Char('r')) => {
+                let life;
+                (life, caption) = reseed();
+                run.replace(life)?;
+                next_step = Instant::now() + delay;
+            }
+            // Edits go straight into the engine, so the simulation stays paused while editing
+            Some(Key::Char('e')) => {
+                let (width, height) = renderer.visible_size(run.life.as_ref());
+                paused = true;
+                cursor = Some(Coord {
+                    x: width as i32 / 2,
+                    y: height as i32 / 2,
+                });
+                keyboard.set_mouse_reporting(true);
+            }
+            // Ctrl-C arrives as a plain byte in raw mode
+            Some(Key::Char('q' | '\u{3}')) => break,
+            Some(_) => {}
+        }
+    }
+    run.finish()?;
+    Ok(run)
+}